@@ -1,12 +1,9 @@
 pub fn wrap(angle: f32) -> f32{
     let mut ref_angle = angle.rem_euclid(2.0 * std::f32::consts::PI);
 
-    if ref_angle.is_sign_negative()
+    if ref_angle.is_sign_negative() && ref_angle >= -std::f32::EPSILON
     {
-        if ref_angle >= -std::f32::EPSILON
-        {
-            ref_angle = 0.0;
-        }
+        ref_angle = 0.0;
     }
 
     let mut wrapped_angle = ref_angle;
@@ -4,9 +4,14 @@ use crate::proximity::Proximity;
 use crate::proximity::ProximitySettings;
 use crate::gain::Gain;
 use crate::speed::Speed;
+use crate::rng::Rng;
+use crate::tuning::Tuning;
+use crate::obstacle::Avoidance;
+use crate::boundary::Boundary;
+use crate::containment::Edge;
 
 #[derive(Copy,Clone,Debug,PartialEq)]
-enum State{
+pub enum State{
     Idle,
     TurningH,
     TurningV,
@@ -14,7 +19,37 @@ enum State{
     TurningHarderV,
 }
 
-const TRAIL_LEN:usize = 64;
+impl State{
+    pub fn name(&self) -> &'static str{
+        match self{
+            State::Idle => "Idle",
+            State::TurningH => "TurningH",
+            State::TurningV => "TurningV",
+            State::TurningHarderH => "TurningHarderH",
+            State::TurningHarderV => "TurningHarderV",
+        }
+    }
+}
+
+pub(crate) const TRAIL_LEN:usize = 64;
+
+/* Bird default settings, speeds and turn rates are per second.
+ * They were originally tuned per frame at 60 frames a second */
+const PER_SECOND:f32 = 60.0;
+
+const SPEED_GAIN:f32 = 1.4 * PER_SECOND;
+const DEFAULT_BIRD_SPEED_MIN:f32 = 1.0 * SPEED_GAIN;
+const DEFAULT_BIRD_SPEED_MAX:f32 = 7.5 * SPEED_GAIN;
+
+const DEFAULT_SEP_SPEED_MIN:f32 = 1.25 * SPEED_GAIN;
+const DEFAULT_SEP_SPEED_MAX:f32 = 2.5 * SPEED_GAIN;
+
+const DEFAULT_COH_SPEED_MIN:f32 = 0.5 * SPEED_GAIN;
+const DEFAULT_COH_SPEED_MAX:f32 = 1.5 * SPEED_GAIN;
+    
+const DEFAULT_SEP_DELTA:f32 = 0.00625 * 2.4 * PER_SECOND;
+const DEFAULT_COH_DELTA:f32 = 0.00005625 * 3.0 * PER_SECOND;
+const DEFAULT_ALIGNMENT_GAIN:f32 = 0.0275 * PER_SECOND;
 
 /* struct used to initialise the bird */
 #[derive(Copy, Clone)]
@@ -23,6 +58,25 @@ pub struct BirdConfig{
     pub cohesion:ProximitySettings,
     pub alignment_gain:Gain,
     pub speed:Speed,
+    pub tuning:Tuning,
+}
+
+impl BirdConfig{
+    pub fn new() -> BirdConfig{
+        BirdConfig{
+            separation:ProximitySettings::new(Speed::new(DEFAULT_SEP_SPEED_MIN,DEFAULT_SEP_SPEED_MAX, true), DEFAULT_SEP_DELTA),
+            cohesion:ProximitySettings::new(Speed::new(DEFAULT_COH_SPEED_MIN,DEFAULT_COH_SPEED_MAX, true), -DEFAULT_COH_DELTA),  
+            alignment_gain: Gain::new(DEFAULT_ALIGNMENT_GAIN),
+            speed: Speed::new(DEFAULT_BIRD_SPEED_MIN, DEFAULT_BIRD_SPEED_MAX, true),
+            tuning: Tuning::new(),
+        }
+    }
+}
+
+impl Default for BirdConfig{
+    fn default() -> BirdConfig{
+        BirdConfig::new()
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -32,34 +86,53 @@ pub struct Bird{
     align_angle: f32,
     state:State,
     speed:Speed,
-    turn_angle:f32,
+    turn_angle:f32, // radians per second
+    airspeed:f32, // vector model only
 
     trail:[Point2; TRAIL_LEN],
     trail_pos:usize,
     
     separation:Proximity,
     cohesion:Proximity,
+    avoidance:Option<Avoidance>,
+    threat:Option<Avoidance>,
+    lure:Option<Avoidance>,
+    force:Option<Vec2>,
+    edge:Option<Edge>,
     alignment_gain:Gain,
+    tuning:Tuning,
+}
+
+/* Just the parts of a bird that change as it flies, kept for rewinding. The rest comes
+ * from the bird config and the trail is rebuilt from earlier snapshots, so it's a
+ * fraction of the size of the bird itself */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct BirdSnapshot{
+    xy: Point2,
+    angle: f32,
+    align_angle: f32,
+    state:State,
+    turn_angle:f32,
+    airspeed:f32,
+    separation:(f32, f32),
+    cohesion:(f32, f32),
+}
+
+impl BirdSnapshot{
+    pub(crate) fn position(&self) -> Point2{
+        self.xy
+    }
 }
 
 impl Bird{
     const BIRD_HEIGHT:f32 = 30.0;
     const BIRD_WIDTH_2:f32 = 10.0;
 
-    const EDGE_BLEED:f32 = 50.0;
-
-    const BIRD_REGION_RADIUS:f32 = 225.0; 
-    const BIRD_SEPARATION_RADIUS:f32 = 30.0;
-
     const ALIGNMENT_INITIAL:f32 = 0.0;
 
-    const TURN_GAIN:f32 = 0.020;
-
-    const HARD_ANGLE_MULTIPLIER:f32 = 5.0;
-    const HARD_ANGLE_SATURATION:f32 = 65.0;
-    
     const NON_ZERO_ADJUST:f32 = 0.001;
-    const DISTANCE_DECAY:f32 = 0.1;
+
+    const CONE_SEGMENTS:usize = 32;
 
     pub fn new(position:Point2, angle:f32, config: BirdConfig) -> Bird{
         Bird{
@@ -68,12 +141,19 @@ impl Bird{
             align_angle: Self::ALIGNMENT_INITIAL,
             state: State::Idle,
             turn_angle: 0.0,
+            airspeed: (config.speed.min() + config.speed.max()) / 2.0,
             trail: [position; TRAIL_LEN],
             trail_pos: 0,
             separation: Proximity::new(config.separation, angle, 0.0), 
             cohesion: Proximity::new(config.cohesion,angle, 0.0),
+            avoidance: None,
+            threat: None,
+            lure: None,
+            force: None,
+            edge: None,
             alignment_gain: Gain::new(config.alignment_gain.gain()),
             speed: config.speed,
+            tuning: config.tuning,
         }
     }
 
@@ -81,19 +161,24 @@ impl Bird{
         self.angle
     }
 
+    pub fn state(&self) -> State{
+        self.state
+    }
+
     pub fn refresh_settings(&mut self, config: &mut BirdConfig){
         self.separation.refresh_settings(&config.separation);
         self.cohesion.refresh_settings(&config.cohesion);
         self.alignment_gain.set(config.alignment_gain.gain());
         self.speed = config.speed;
+        self.tuning = config.tuning;
     }
 
     pub fn set_rotation(&mut self, new_rotation:f32){
         self.angle = new_rotation;
     }
     
-    pub fn set_separation(&mut self, new_rotation:f32, new_angle:f32){
-        self.separation.update(new_rotation, new_angle);
+    pub fn set_separation(&mut self, new_rotation:f32, new_angle:f32, strength:f32){
+        self.separation.update(new_rotation, new_angle, strength);
     }
     
     pub fn get_separation(&self) -> f32{
@@ -112,16 +197,100 @@ impl Bird{
         self.align_angle = new_rotation;
     }
     
-    pub fn set_cohesion(&mut self, new_rotation:f32, new_angle:f32){
-        self.cohesion.update(new_rotation, new_angle);
+    pub fn set_cohesion(&mut self, new_rotation:f32, new_angle:f32, strength:f32){
+        self.cohesion.update(new_rotation, new_angle, strength);
+    }
+
+    pub fn separation(&self) -> Proximity{
+        self.separation
+    }
+
+    pub fn cohesion(&self) -> Proximity{
+        self.cohesion
+    }
+
+    /* Radians per second, while turning back from the edges */
+    pub fn turn_angle(&self) -> f32{
+        self.turn_angle
+    }
+
+    pub fn set_avoidance(&mut self, avoidance:Option<Avoidance>){
+        self.avoidance = avoidance;
+    }
+
+    /* Nearest predator, as the direction away from it and how far away it is */
+    pub fn set_threat(&mut self, threat:Option<Avoidance>){
+        self.threat = threat;
+    }
+
+    pub fn fear_radius(&self) -> f32{
+        self.tuning.fear_radius
+    }
+
+    /* Heading towards an attractor or away from a repeller, and how far away it is */
+    pub fn set_lure(&mut self, lure:Option<Avoidance>){
+        self.lure = lure;
+    }
+
+    /* Steering force from the vector model, None to steer with the angular model */
+    pub fn set_force(&mut self, force:Option<Vec2>){
+        self.force = force;
+    }
+
+    /* Where the bird is relative to the edge of a containment shape, None to turn back at the bounds */
+    pub fn set_edge(&mut self, edge:Option<Edge>){
+        self.edge = edge;
+    }
+
+    /* Direction and speed the bird flies at under the vector model */
+    pub fn velocity(&self) -> Vec2{
+        vec2(self.angle.cos(), self.angle.sin()) * self.airspeed
+    }
+
+    pub fn max_speed(&self) -> f32{
+        self.speed.max()
+    }
+
+    pub fn max_force(&self) -> f32{
+        self.tuning.max_force
+    }
+
+    pub fn tuning(&self) -> &Tuning{
+        &self.tuning
+    }
+
+    pub fn pointer_radius(&self) -> f32{
+        self.tuning.pointer_radius
+    }
+
+    pub fn obstacle_radius(&self) -> f32{
+        self.tuning.obstacle_radius
     }
 
     pub fn radius(&self) -> f32{
-        Self::BIRD_REGION_RADIUS
+        self.tuning.region_radius
+    }
+
+    /* Vision cones for alignment/cohesion and for separation, in radians */
+    pub fn field_of_view(&self) -> f32{
+        deg_to_rad(self.tuning.field_of_view)
+    }
+
+    pub fn separation_field_of_view(&self) -> f32{
+        deg_to_rad(self.tuning.separation_field_of_view)
+    }
+
+    /* Whether something at this offset from the bird falls inside a cone of the given width
+     * centred on its heading, anything behind it is in the blind spot */
+    pub fn sees(&self, offset:Vec2, field_of_view:f32) -> bool{
+        if field_of_view >= deg_to_rad(360.0){
+            return true;
+        }
+        angle::wrap_180(offset.y.atan2(offset.x) - self.angle).abs() <= field_of_view / 2.0
     }
     
     pub fn separation_radius(&self) -> f32{
-        Self::BIRD_SEPARATION_RADIUS
+        self.tuning.separation_radius
     }
     
     pub fn position(&self) -> Point2{
@@ -134,20 +303,36 @@ impl Bird{
 
     pub fn draw_region(&self, draw: &Draw)
     {
-        draw.ellipse()
-            .color(GREY)
-            .x_y(self.xy.x, self.xy.y)
-            .w(Self::BIRD_REGION_RADIUS * 2.0)
-            .h(Self::BIRD_REGION_RADIUS * 2.0);
+        self.draw_cone(draw, self.tuning.region_radius, self.field_of_view(), GREY);
     }
     
     pub fn draw_sep_region(&self, draw: &Draw)
     {
-        draw.ellipse()
-            .color(CYAN)
-            .x_y(self.xy.x, self.xy.y)
-            .w(Self::BIRD_SEPARATION_RADIUS * 2.0)
-            .h(Self::BIRD_SEPARATION_RADIUS * 2.0);
+        self.draw_cone(draw, self.tuning.separation_radius, self.separation_field_of_view(), CYAN);
+    }
+
+    /* Full circle when the bird sees all round, otherwise a sector with the blind spot cut out */
+    fn draw_cone(&self, draw: &Draw, radius:f32, field_of_view:f32, colour:Srgb<u8>)
+    {
+        if field_of_view >= deg_to_rad(360.0)
+        {
+            draw.ellipse()
+                .color(colour)
+                .x_y(self.xy.x, self.xy.y)
+                .w(radius * 2.0)
+                .h(radius * 2.0);
+            return;
+        }
+
+        let start = self.angle - (field_of_view / 2.0);
+        let points = (0..=Self::CONE_SEGMENTS).map(|i|{
+            let angle = start + (field_of_view * i as f32 / Self::CONE_SEGMENTS as f32);
+            pt2(self.xy.x + (radius * angle.cos()), self.xy.y + (radius * angle.sin()))
+        });
+
+        draw.polygon()
+            .color(colour)
+            .points(std::iter::once(self.xy).chain(points));
     }
 
     pub fn draw_trail(&self, draw: &Draw)
@@ -183,52 +368,180 @@ impl Bird{
             .color(WHITE);
     }
 
-    pub fn update(&mut self, win: &Rect<f32>, inner: &Rect<f32>, inner_hard: &Rect<f32>)
+    /* Ring around the bird, e.g. when it's selected */
+    pub fn draw_highlight(&self, draw: &Draw)
+    {
+        draw.ellipse()
+            .xy(self.xy)
+            .w_h(Self::BIRD_HEIGHT * 1.5, Self::BIRD_HEIGHT * 1.5)
+            .no_fill()
+            .stroke_weight(2.0)
+            .stroke(YELLOW);
+    }
+
+    /* Advance the bird by dt seconds */
+    pub fn update(&mut self, boundary: Boundary, win: &Rect<f32>, inner: &Rect<f32>, inner_hard: &Rect<f32>, dt: f32, rng: &mut Rng)
     {
         assert!(self.angle >= 0.0);
 
         let mut align_gain = self.alignment_gain.gain();
-        let near_edge = self.is_near_edge(inner);
+        let mut attenuation = 1.0;
+        let edge = self.edge.take();
+        let near_edge = boundary.turns() && match edge{
+            Some(edge) => edge.distance > 0.0,
+            None => self.is_near_edge(inner),
+        };
 
         if near_edge 
         {
-            let dist = self.distance_outside(inner); 
-            let reduct = (dist * -Self::DISTANCE_DECAY).exp();
+            let dist = match edge{
+                Some(edge) => edge.distance,
+                None => self.distance_outside(inner),
+            };
+            let reduct = (dist * -self.tuning.distance_decay).exp();
             self.separation.attenuate_angle(reduct);
             self.cohesion.attenuate_angle(reduct);
             align_gain *= reduct;
+            attenuation *= reduct;
+        }
+
+        /* The closer a predator is the more fleeing takes over from flocking */
+        let threat = self.threat.take();
+        let fear = threat.map_or(0.0, |t| self.fear(t));
+        if fear > 0.0
+        {
+            self.separation.attenuate_angle(1.0 - fear);
+            self.cohesion.attenuate_angle(1.0 - fear);
+            align_gain *= 1.0 - fear;
+            attenuation *= 1.0 - fear;
         }
 
-        /* Separation */
-        if self.separation.changed(){
-            assert!(self.separation.settings().delta().is_positive());
-            self.apply_proximity(self.separation);
-            self.separation.reset();
+        let force = self.force.take();
+        if let Some(force) = force
+        {
+            /* Vector model, all three rules in one */
+            self.apply_force(force * attenuation, dt);
         }
-        
-        /* Cohesion */
-        if self.cohesion.changed(){
-            assert!(self.cohesion.settings().delta().is_negative());
-            self.apply_proximity(self.cohesion);
-            self.cohesion.reset();
+        else
+        {
+            /* Separation */
+            if self.separation.changed(){
+                assert!(self.separation.settings().delta().is_positive());
+                self.apply_proximity(self.separation, dt, rng);
+                self.separation.reset();
+            }
+            
+            /* Cohesion */
+            if self.cohesion.changed(){
+                assert!(self.cohesion.settings().delta().is_negative());
+                self.apply_proximity(self.cohesion, dt, rng);
+                self.cohesion.reset();
+            }
+            
+            /* Adjust Alignment */
+            self.angle += self.align_angle * align_gain * dt;
+            self.angle = angle::wrap(self.angle);
         }
         
-        /* Adjust Alignment */
-        self.angle += self.align_angle * align_gain;
-        self.angle = angle::wrap(self.angle);
-        
         assert!(self.angle != std::f32::INFINITY);
         assert!(self.angle != std::f32::NEG_INFINITY);
         assert!(self.angle >= 0.0);
 
-        self.move_rnd(self.speed.min(), self.speed.max());
+        /* Mouse pointer */
+        if let Some(lure) = self.lure.take(){
+            self.apply_lure(lure, dt);
+        }
+
+        /* Predators */
+        if let Some(threat) = threat{
+            self.apply_flee(threat, dt);
+        }
+
+        /* Obstacles */
+        if let Some(avoidance) = self.avoidance.take(){
+            self.apply_avoidance(avoidance, dt);
+        }
+
+        /* Frightened birds fly flat out */
+        if force.is_some()
+        {
+            let airspeed = self.airspeed + ((self.speed.max() - self.airspeed) * fear);
+            self.move_bird(airspeed * dt);
+        }
+        else
+        {
+            let speed_min = self.speed.min() + ((self.speed.max() - self.speed.min()) * fear);
+            self.move_rnd(speed_min, self.speed.max(), dt, rng);
+        }
 
-        self.state_machine(win, inner, inner_hard);
-        self.screen_wrap(win);
+        if !boundary.turns()
+        {
+            self.state = State::Idle;
+        }
+        else if let Some(edge) = edge
+        {
+            self.state = State::Idle;
+            self.apply_containment(edge, dt);
+        }
+        else
+        {
+            self.state_machine(win, inner, inner_hard, dt, rng);
+        }
+        boundary.contain(&mut self.xy, &mut self.angle, win, self.tuning.edge_bleed);
 
         self.update_trail();
     }
 
+    pub(crate) fn snapshot(&self) -> BirdSnapshot
+    {
+        BirdSnapshot{
+            xy: self.xy,
+            angle: self.angle,
+            align_angle: self.align_angle,
+            state: self.state,
+            turn_angle: self.turn_angle,
+            airspeed: self.airspeed,
+            separation: (self.separation.angle(), self.separation.alignment()),
+            cohesion: (self.cohesion.angle(), self.cohesion.alignment()),
+        }
+    }
+
+    /* Bring a bird back from a snapshot, its trail running through the earlier positions given oldest first */
+    pub(crate) fn restore<I>(snapshot:&BirdSnapshot, trail:I, config:BirdConfig) -> Bird
+        where I: Iterator<Item = Point2>
+    {
+        let mut bird = Bird::new(snapshot.xy, snapshot.angle, config);
+        for position in trail{
+            bird.xy = position;
+            bird.update_trail();
+        }
+
+        bird.xy = snapshot.xy;
+        bird.align_angle = snapshot.align_angle;
+        bird.state = snapshot.state;
+        bird.turn_angle = snapshot.turn_angle;
+        bird.airspeed = snapshot.airspeed;
+        bird.separation = Proximity::new(config.separation, snapshot.separation.0, snapshot.separation.1);
+        bird.cohesion = Proximity::new(config.cohesion, snapshot.cohesion.0, snapshot.cohesion.1);
+        bird
+    }
+
+    /* Copy of the bird somewhere else, e.g. where a neighbour across the edge of a torus appears to be */
+    pub fn moved_to(&self, position:Point2) -> Bird
+    {
+        let mut bird = *self;
+        bird.xy = position;
+        bird
+    }
+
+    /* Move the bird straight to a recorded position and angle, the trail follows as if it had flown there */
+    pub fn place(&mut self, position:Point2, angle:f32)
+    {
+        self.xy = position;
+        self.angle = angle;
+        self.update_trail();
+    }
+
     fn update_trail(&mut self)
     {
         self.trail[self.trail_pos] = self.xy;
@@ -237,7 +550,7 @@ impl Bird{
 
     }
 
-    fn state_machine(&mut self, _win: &Rect<f32>, inner: &Rect<f32>, inner_hard: &Rect<f32>)
+    fn state_machine(&mut self, _win: &Rect<f32>, inner: &Rect<f32>, inner_hard: &Rect<f32>, dt: f32, rng: &mut Rng)
     {
         match self.state{
             State::Idle =>
@@ -258,7 +571,7 @@ impl Bird{
                         assert!(turn_angle != 0.0);
                         assert!(turn_angle >= -std::f32::consts::PI);
                         assert!(turn_angle <= std::f32::consts::PI);
-                        self.turn_angle = turn_angle * Self::TURN_GAIN;
+                        self.turn_angle = turn_angle * self.tuning.turn_gain;
                     }
                     else if self.xy.x < inner.left() as f32
                     {
@@ -273,7 +586,7 @@ impl Bird{
                         assert!(turn_angle != 0.0);
                         assert!(turn_angle >= -std::f32::consts::PI);
                         assert!(turn_angle <= std::f32::consts::PI);
-                        self.turn_angle = turn_angle * Self::TURN_GAIN;
+                        self.turn_angle = turn_angle * self.tuning.turn_gain;
 
                     }
                     else
@@ -296,7 +609,7 @@ impl Bird{
                         assert!(turn_angle != 0.0);
                         assert!(turn_angle >= -180.0);
                         assert!(turn_angle <= 180.0);
-                        self.turn_angle = turn_angle * Self::TURN_GAIN;
+                        self.turn_angle = turn_angle * self.tuning.turn_gain;
                     }
                     else if self.xy.y < inner.bottom() as f32
                     {
@@ -311,7 +624,7 @@ impl Bird{
                         assert!(turn_angle != 0.0);
                         assert!(turn_angle >= -180.0);
                         assert!(turn_angle <= 180.0);
-                        self.turn_angle = turn_angle * Self::TURN_GAIN;
+                        self.turn_angle = turn_angle * self.tuning.turn_gain;
                     }
                     else
                     {
@@ -324,9 +637,9 @@ impl Bird{
             State::TurningH =>
             {
                 
-                self.angle += self.turn_angle;
+                self.angle += self.turn_angle * dt;
                 self.angle = angle::wrap(self.angle);
-                self.move_rnd(self.speed.min() * 0.5, self.speed.max()* 0.5); 
+                self.move_rnd(self.speed.min() * 0.5, self.speed.max()* 0.5, dt, rng); 
 
                 if !self.h_is_near_edge(inner)
                 {
@@ -344,9 +657,9 @@ impl Bird{
             State::TurningV =>
             {
                 
-                self.angle += self.turn_angle;
+                self.angle += self.turn_angle * dt;
                 self.angle = angle::wrap(self.angle);
-                self.move_rnd(self.speed.min() * 0.5, self.speed.max() * 0.5); 
+                self.move_rnd(self.speed.min() * 0.5, self.speed.max() * 0.5, dt, rng); 
 
                 if !self.v_is_near_edge(inner)
                 {
@@ -363,9 +676,9 @@ impl Bird{
             },
             State::TurningHarderH =>
             {
-                self.angle += self.saturate_angle(self.turn_angle * Self::HARD_ANGLE_MULTIPLIER, deg_to_rad(Self::HARD_ANGLE_SATURATION));
+                self.angle += self.saturate_angle(self.turn_angle * self.tuning.hard_angle_multiplier, deg_to_rad(self.tuning.hard_angle_saturation)) * dt;
                 self.angle = angle::wrap(self.angle);
-                self.move_rnd(self.speed.min(), self.speed.max()); 
+                self.move_rnd(self.speed.min(), self.speed.max(), dt, rng); 
 
                 if !self.h_is_near_edge(inner_hard)
                 {
@@ -374,9 +687,9 @@ impl Bird{
             },
             State::TurningHarderV =>
             {
-                self.angle += self.saturate_angle(self.turn_angle * Self::HARD_ANGLE_MULTIPLIER, deg_to_rad(Self::HARD_ANGLE_SATURATION));
+                self.angle += self.saturate_angle(self.turn_angle * self.tuning.hard_angle_multiplier, deg_to_rad(self.tuning.hard_angle_saturation)) * dt;
                 self.angle = angle::wrap(self.angle);
-                self.move_rnd(self.speed.min(), self.speed.max());
+                self.move_rnd(self.speed.min(), self.speed.max(), dt, rng);
 
                 if !self.v_is_near_edge(inner_hard)
                 {
@@ -387,9 +700,9 @@ impl Bird{
 
     }
 
-    fn move_rnd(&mut self, lower_speed:f32, upper_speed:f32)
+    fn move_rnd(&mut self, lower_speed:f32, upper_speed:f32, dt:f32, rng: &mut Rng)
     {
-        let mov_inc = random_range(lower_speed, upper_speed); 
+        let mov_inc = rng.range(lower_speed, upper_speed) * dt; 
         self.move_bird(mov_inc);
     }
 
@@ -407,16 +720,7 @@ impl Bird{
 
     fn saturate_angle(&self, angle:f32, limit:f32)->f32
     {
-        let mut new_angle = angle;
-        if angle >= limit
-        {
-            new_angle = limit;
-        }
-        else if angle <= -limit
-        {
-            new_angle = limit;
-        }
-        new_angle
+        angle.min(limit).max(-limit)
     }
 
     pub fn rotation_delta(&self, position: Point2, angle: f32, rot_angle: f32) -> f32
@@ -448,20 +752,19 @@ impl Bird{
     }
 
 
-    pub fn apply_proximity(&mut self, prox:Proximity)
+    pub fn apply_proximity(&mut self, prox:Proximity, dt:f32, rng: &mut Rng)
     {
         assert!(prox.angle() >= -std::f32::consts::PI);
         assert!(prox.angle() <= std::f32::consts::PI);
         /* Randomise movement */
-        let mov_inc:f32;
-        
-        if prox.settings().speed().randomise(){
-            mov_inc = random_range(prox.settings().speed().min(), prox.settings().speed().max());
+        let mov_inc = if prox.settings().speed().randomise(){
+            rng.range(prox.settings().speed().min(), prox.settings().speed().max())
         }
         else
         {
-            mov_inc = prox.settings().speed().max();
-        }
+            prox.settings().speed().max()
+        };
+        let mov_inc = mov_inc * dt;
         let old_xy = self.xy;
         
         /* 1. Move bird in direction of proximity angle */
@@ -476,8 +779,9 @@ impl Bird{
         let rotated_position = self.rotate(old_xy, angle_offset);
         let norm_angle = angle::wrap( self.angle - prox.alignment() );
 
-        /* 4. Determine whether to add or subtract an angle to turn away as appropriate */
-        let delta:f32 = self.rotation_delta(rotated_position, norm_angle, prox.settings().delta());
+        /* 4. Determine whether to add or subtract an angle to turn away as appropriate,
+         * nearer neighbours turning the bird harder depending on the weighting */
+        let delta:f32 = self.rotation_delta(rotated_position, norm_angle, prox.settings().delta() * prox.strength() * dt);
 
         self.angle += delta;
         self.angle = angle::wrap(self.angle);
@@ -487,6 +791,89 @@ impl Bird{
 
     }    
 
+    /* Accelerate by a steering force, keeping within the bird's speed limits */
+    pub fn apply_force(&mut self, force:Vec2, dt:f32)
+    {
+        let velocity = self.velocity() + (force * dt);
+        self.airspeed = velocity.length().clamp(self.speed.min(), self.speed.max());
+        if velocity != Vec2::ZERO
+        {
+            self.angle = angle::wrap(velocity.y.atan2(velocity.x));
+        }
+    }
+
+    /* Turn back into a containment shape once past the turn offset, ramping up to the
+     * hard turn at the hard turn offset */
+    pub fn apply_containment(&mut self, edge:Edge, dt:f32)
+    {
+        if edge.distance <= 0.0
+        {
+            return;
+        }
+
+        let band = edge.distance - edge.distance_hard;
+        let ramp = if band > 0.0 { (edge.distance / band).min(1.0) } else { 1.0 };
+        let gain = self.tuning.turn_gain * (1.0 + ((self.tuning.hard_angle_multiplier - 1.0) * ramp));
+        self.turn_towards(edge.angle, gain, dt);
+    }
+
+    /* Turn to skim past an obstacle rather than straight away from it, turning harder the
+     * closer it is. Birds that have ended up inside one head straight back out */
+    pub fn apply_avoidance(&mut self, avoidance:Avoidance, dt:f32)
+    {
+        let away = avoidance.angle;
+        let heading_delta = angle::wrap_180(away - self.angle);
+        if avoidance.distance > 0.0 && heading_delta.abs() <= std::f32::consts::PI / 2.0
+        {
+            /* Already heading away from it */
+            return;
+        }
+
+        let target:f32;
+        let strength:f32;
+        if avoidance.distance <= 0.0
+        {
+            target = away;
+            strength = 1.0;
+        }
+        else
+        {
+            let left = angle::wrap_180(away + (std::f32::consts::PI / 2.0) - self.angle);
+            let right = angle::wrap_180(away - (std::f32::consts::PI / 2.0) - self.angle);
+            target = if left.abs() < right.abs() { away + (std::f32::consts::PI / 2.0) } else { away - (std::f32::consts::PI / 2.0) };
+            strength = (1.0 - (avoidance.distance / self.tuning.obstacle_radius)).clamp(0.0, 1.0);
+        }
+
+        self.turn_towards(target, self.tuning.obstacle_gain * strength, dt);
+    }
+
+    /* 1.0 with a predator right on top of the bird, falling to 0.0 at the fear radius */
+    fn fear(&self, threat:Avoidance) -> f32
+    {
+        (1.0 - (threat.distance / self.tuning.fear_radius)).clamp(0.0, 1.0)
+    }
+
+    /* Turn to fly directly away from a predator */
+    pub fn apply_flee(&mut self, threat:Avoidance, dt:f32)
+    {
+        self.turn_towards(threat.angle, self.tuning.fear_gain * self.fear(threat), dt);
+    }
+
+    /* Turn towards an attractor or away from a repeller, harder the closer it is */
+    pub fn apply_lure(&mut self, lure:Avoidance, dt:f32)
+    {
+        let strength = (1.0 - (lure.distance / self.tuning.pointer_radius)).clamp(0.0, 1.0);
+        self.turn_towards(lure.angle, self.tuning.pointer_gain * strength, dt);
+    }
+
+    /* Close a proportion of the gap to the target heading, never overshooting it */
+    fn turn_towards(&mut self, target:f32, gain:f32, dt:f32)
+    {
+        let delta = angle::wrap_180(target - self.angle);
+        let turn = delta * (gain * dt).min(1.0);
+        self.angle = angle::wrap(self.angle + turn);
+    }
+
     fn is_near_edge(&self, inner: &Rect<f32>) -> bool
     {
         let mut near_edge = false;
@@ -520,22 +907,6 @@ impl Bird{
         near_edge
     }
 
-    fn screen_wrap(&mut self, win: &Rect<f32>){
-        if self.xy.x >= win.right() + Self::EDGE_BLEED as f32{
-            self.xy.x -= win.wh().x + Self::EDGE_BLEED;
-        }
-        else if self.xy.x <= win.left() -Self::EDGE_BLEED as f32{
-            self.xy.x += win.wh().x + Self::EDGE_BLEED;
-        }
-        
-        if self.xy.y >= win.top() + Self::EDGE_BLEED as f32{
-            self.xy.y -= win.wh().y + Self::EDGE_BLEED;
-        }
-        else if self.xy.y <= win.bottom() - Self::EDGE_BLEED as f32{
-            self.xy.y += win.wh().y + Self::EDGE_BLEED;
-        } 
-    }
-
     pub fn rotate(&self, source:Point2, angle: f32) -> Point2{
         let x = (source.x * angle.cos()) - (source.y * angle.sin());
         let y = (source.x * angle.sin()) + (source.y * angle.cos());
@@ -582,6 +953,7 @@ mod tests {
             cohesion: ProximitySettings::new(Speed::new(speed, speed, false), -rotation_angle),
             alignment_gain: Gain::new(0.0),
             speed: Speed::new(speed, speed, false),
+            tuning: Tuning::new(),
         }
     }
 
@@ -600,7 +972,7 @@ mod tests {
 
         let separation = Proximity::new(config.separation,sep_angle,0.0);
 
-        bird.apply_proximity(separation);
+        bird.apply_proximity(separation, 1.0, &mut Rng::new(0));
 
         let position_step1 = pt2(init_position.x + (speed * 0.5 * sep_angle.cos()), init_position.y + (speed * 0.5 * sep_angle.sin()));
         let expected_position = pt2(position_step1.x + (speed * 0.5 * exp_angle.cos()), position_step1.y + (speed * 0.5 * exp_angle.sin()));
@@ -610,6 +982,30 @@ mod tests {
         assert!(compare_floats(bird.position().y, expected_position.y, FLOAT_PRECISION));
     }
     
+    #[test]
+    fn saturate_both_ways(){
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, BirdConfig::new());
+        assert_eq!(bird.saturate_angle(2.0, 1.0), 1.0);
+        assert_eq!(bird.saturate_angle(-2.0, 1.0), -1.0);
+        assert_eq!(bird.saturate_angle(-0.5, 1.0), -0.5);
+    }
+
+    #[test]
+    fn strength_scales_turn(){
+        let config = default_bird_config();
+        let turn = |strength:f32|{
+            let mut bird = Bird::new(pt2(0.0, 0.0), deg_to_rad(30.0), config);
+            let mut separation = Proximity::new(config.separation, 0.0, 0.0);
+            separation.update(deg_to_rad(45.0), 0.0, strength);
+            bird.apply_proximity(separation, 1.0, &mut Rng::new(0));
+            angle::wrap_180(bird.angle() - deg_to_rad(30.0))
+        };
+
+        /* A neighbour close enough to count three times as much turns the bird three times as hard */
+        assert!(turn(1.0).abs() > 0.0);
+        assert!(compare_floats(turn(3.0), turn(1.0) * 3.0, FLOAT_PRECISION));
+    }
+
     fn test_cohesion(init_position:Point2, bird_angle:f32, sep_angle:f32, exp_angle:f32)
     {
         let speed = 1.0;
@@ -625,7 +1021,7 @@ mod tests {
 
         let cohesion = Proximity::new(config.cohesion,sep_angle,0.0);
 
-        bird.apply_proximity(cohesion);
+        bird.apply_proximity(cohesion, 1.0, &mut Rng::new(0));
 
         let position_step1 = pt2(init_position.x + (speed * 0.5 * sep_angle.cos()), init_position.y + (speed * 0.5 * sep_angle.sin()));
         let expected_position = pt2(position_step1.x + (speed * 0.5 * exp_angle.cos()), position_step1.y + (speed * 0.5 * exp_angle.sin()));
@@ -643,6 +1039,7 @@ mod tests {
             cohesion: ProximitySettings::new(Speed::new(speed,speed,false),-rotation_angle),
             alignment_gain: Gain::new(0.0),
             speed: Speed::new(speed, speed, false),
+            tuning: Tuning::new(),
         };
         
         let bird = Bird::new(init_position, bird_angle, config);
@@ -1088,6 +1485,133 @@ mod tests {
         }
     }
 
+    #[test]
+    fn avoid_obstacle_ahead(){
+        let config = default_bird_config();
+
+        /* Heading straight at an obstacle to the east, turns off towards whichever side is nearer */
+        let mut bird = Bird::new(pt2(0.0, 0.0), deg_to_rad(10.0), config);
+        bird.apply_avoidance(Avoidance{angle: std::f32::consts::PI, distance: 50.0}, 0.01);
+        assert!(bird.angle() > deg_to_rad(10.0));
+        assert!(bird.angle() < deg_to_rad(90.0));
+
+        let mut bird = Bird::new(pt2(0.0, 0.0), deg_to_rad(350.0), config);
+        bird.apply_avoidance(Avoidance{angle: std::f32::consts::PI, distance: 50.0}, 0.01);
+        assert!(bird.angle() < deg_to_rad(350.0));
+        assert!(bird.angle() > deg_to_rad(270.0));
+    }
+
+    #[test]
+    fn ignore_obstacle_behind(){
+        let config = default_bird_config();
+        let mut bird = Bird::new(pt2(0.0, 0.0), deg_to_rad(170.0), config);
+        bird.apply_avoidance(Avoidance{angle: std::f32::consts::PI, distance: 50.0}, 0.01);
+        assert_eq!(bird.angle(), deg_to_rad(170.0));
+    }
+
+    #[test]
+    fn escape_from_inside_obstacle(){
+        let config = default_bird_config();
+        let mut bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        for _i in 0..100{
+            bird.apply_avoidance(Avoidance{angle: std::f32::consts::PI, distance: -5.0}, 0.1);
+        }
+        assert!(compare_floats(bird.angle(), std::f32::consts::PI, 0.001));
+    }
+
+    #[test]
+    fn flee_predator(){
+        let config = default_bird_config();
+
+        /* Predator to the north east, heading north, so turns clockwise towards the south west */
+        let threat = Avoidance{angle: deg_to_rad(225.0), distance: 50.0};
+        let mut bird = Bird::new(pt2(0.0, 0.0), deg_to_rad(90.0), config);
+        bird.apply_flee(threat, 0.01);
+        assert!(bird.angle() > deg_to_rad(90.0));
+        assert!(bird.angle() < deg_to_rad(225.0));
+
+        for _i in 0..1000{
+            bird.apply_flee(threat, 0.01);
+        }
+        assert!(compare_floats(bird.angle(), deg_to_rad(225.0), 0.001));
+
+        /* Out of range, no fear */
+        let mut bird = Bird::new(pt2(0.0, 0.0), deg_to_rad(90.0), config);
+        bird.apply_flee(Avoidance{angle: deg_to_rad(225.0), distance: 1000.0}, 0.01);
+        assert_eq!(bird.angle(), deg_to_rad(90.0));
+    }
+
+    #[test]
+    fn follow_lure(){
+        let config = default_bird_config();
+        let mut bird = Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config);
+        let lure = Avoidance{angle: deg_to_rad(90.0), distance: 100.0};
+
+        bird.apply_lure(lure, 0.01);
+        assert!(bird.angle() > 0.0);
+        assert!(bird.angle() < deg_to_rad(90.0));
+
+        for _i in 0..1000{
+            bird.apply_lure(lure, 0.01);
+        }
+        assert!(compare_floats(bird.angle(), deg_to_rad(90.0), 0.001));
+    }
+
+    #[test]
+    fn blind_spot(){
+        let config = default_bird_config();
+        let bird = Bird::new(pt2(0.0, 0.0), deg_to_rad(90.0), config);
+        let field_of_view = deg_to_rad(270.0);
+
+        assert!(bird.sees(vec2(0.0, 10.0), field_of_view));
+        assert!(bird.sees(vec2(10.0, 0.0), field_of_view));
+        assert!(bird.sees(vec2(10.0, -9.0), field_of_view));
+        assert!(!bird.sees(vec2(0.0, -10.0), field_of_view));
+        assert!(!bird.sees(vec2(-10.0, -11.0), field_of_view));
+
+        assert!(bird.sees(vec2(0.0, -10.0), deg_to_rad(360.0)));
+    }
+
+    #[test]
+    fn turn_back_into_containment(){
+        let config = BirdConfig::new();
+        let edge = |distance:f32| Edge{angle: deg_to_rad(180.0), distance, distance_hard: distance - 170.0};
+
+        /* Not yet in the turn band */
+        let mut bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        bird.apply_containment(edge(-10.0), 0.1);
+        assert_eq!(bird.angle(), 0.0);
+
+        /* Deeper in turns harder */
+        let mut shallow = Bird::new(pt2(0.0, 0.0), deg_to_rad(90.0), config);
+        let mut deep = Bird::new(pt2(0.0, 0.0), deg_to_rad(90.0), config);
+        shallow.apply_containment(edge(20.0), 0.01);
+        deep.apply_containment(edge(200.0), 0.01);
+        assert!(shallow.angle() > deg_to_rad(90.0));
+        assert!(deep.angle() > shallow.angle());
+        assert!(deep.angle() < deg_to_rad(180.0));
+    }
+
+    #[test]
+    fn force_turns_and_limits_speed(){
+        let config = BirdConfig::new();
+        let mut bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+
+        bird.apply_force(vec2(0.0, 100.0), 0.1);
+        assert!(bird.angle() > 0.0 && bird.angle() < deg_to_rad(90.0));
+
+        for _i in 0..100{
+            bird.apply_force(vec2(0.0, 1000.0), 0.1);
+        }
+        assert!(compare_floats(bird.angle(), deg_to_rad(90.0), 0.01));
+        assert!(compare_floats(bird.velocity().length(), config.speed.max(), 0.01));
+
+        for _i in 0..100{
+            bird.apply_force(vec2(0.0, -1000.0), 0.01);
+        }
+        assert!(bird.velocity().length() >= config.speed.min() - 0.01);
+    }
+
     #[test]
     fn rotate_minus_90()
     {
@@ -0,0 +1,135 @@
+use nannou::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::angle;
+
+/* What happens to birds at the edge of the world */
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Boundary{
+    /* Fly off one edge and back in at the opposite one, birds see across the edges too */
+    Torus,
+    /* Turn back inside the turn box, wrapping round if they still bleed past the edge */
+    #[default]
+    TurnBox,
+    /* Bounce off the edges */
+    Reflect,
+    /* No edges at all, the view follows the flock instead */
+    Unbounded,
+}
+
+impl Boundary{
+    pub fn name(&self) -> &'static str{
+        match self{
+            Boundary::Torus => "Torus",
+            Boundary::TurnBox => "Turn Box",
+            Boundary::Reflect => "Reflect",
+            Boundary::Unbounded => "Unbounded",
+        }
+    }
+
+    /* Whether birds steer away from the edges before they reach them */
+    pub fn turns(&self) -> bool{
+        *self == Boundary::TurnBox
+    }
+
+    /* Keep a position that has just moved inside the world, turning the heading with it if
+     * it bounces. Bleed is how far past the edge a bird in the turn box can go before wrapping */
+    pub fn contain(&self, position:&mut Point2, heading:&mut f32, win:&Rect<f32>, bleed:f32){
+        match self{
+            Boundary::Torus => wrap(position, win, 0.0),
+            Boundary::TurnBox => wrap(position, win, bleed),
+            Boundary::Reflect => reflect(position, heading, win),
+            Boundary::Unbounded => {},
+        }
+    }
+}
+
+fn wrap(position:&mut Point2, win:&Rect<f32>, bleed:f32){
+    if position.x >= win.right() + bleed{
+        position.x -= win.w() + bleed;
+    }
+    else if position.x <= win.left() - bleed{
+        position.x += win.w() + bleed;
+    }
+
+    if position.y >= win.top() + bleed{
+        position.y -= win.h() + bleed;
+    }
+    else if position.y <= win.bottom() - bleed{
+        position.y += win.h() + bleed;
+    }
+}
+
+/* Mirror whatever went past the edge back inside, and the heading with it */
+fn reflect(position:&mut Point2, heading:&mut f32, win:&Rect<f32>){
+    if position.x > win.right(){
+        position.x = (2.0 * win.right()) - position.x;
+        *heading = angle::wrap(std::f32::consts::PI - *heading);
+    }
+    else if position.x < win.left(){
+        position.x = (2.0 * win.left()) - position.x;
+        *heading = angle::wrap(std::f32::consts::PI - *heading);
+    }
+
+    if position.y > win.top(){
+        position.y = (2.0 * win.top()) - position.y;
+        *heading = angle::wrap(-*heading);
+    }
+    else if position.y < win.bottom(){
+        position.y = (2.0 * win.bottom()) - position.y;
+        *heading = angle::wrap(-*heading);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const FLOAT_PRECISION:f32 = 0.0001;
+
+    fn win() -> Rect<f32>{
+        Rect::from_w_h(1000.0, 1000.0)
+    }
+
+    #[test]
+    fn torus_and_turn_box_wrap(){
+        let mut position = pt2(510.0, 0.0);
+        let mut heading = 0.0;
+        Boundary::Torus.contain(&mut position, &mut heading, &win(), 50.0);
+        assert!((position.x - (-490.0)).abs() < FLOAT_PRECISION);
+
+        /* Still within the bleed */
+        let mut position = pt2(510.0, 0.0);
+        Boundary::TurnBox.contain(&mut position, &mut heading, &win(), 50.0);
+        assert_eq!(position, pt2(510.0, 0.0));
+
+        let mut position = pt2(0.0, -560.0);
+        Boundary::TurnBox.contain(&mut position, &mut heading, &win(), 50.0);
+        assert!((position.y - 490.0).abs() < FLOAT_PRECISION);
+        assert_eq!(heading, 0.0);
+    }
+
+    #[test]
+    fn reflect_bounces(){
+        let mut position = pt2(520.0, 0.0);
+        let mut heading = deg_to_rad(30.0);
+        Boundary::Reflect.contain(&mut position, &mut heading, &win(), 50.0);
+        assert!((position.x - 480.0).abs() < FLOAT_PRECISION);
+        assert!((heading - deg_to_rad(150.0)).abs() < FLOAT_PRECISION);
+
+        let mut position = pt2(0.0, -510.0);
+        let mut heading = deg_to_rad(270.0);
+        Boundary::Reflect.contain(&mut position, &mut heading, &win(), 50.0);
+        assert!((position.y - (-490.0)).abs() < FLOAT_PRECISION);
+        assert!((heading - deg_to_rad(90.0)).abs() < FLOAT_PRECISION);
+    }
+
+    #[test]
+    fn unbounded_leaves_alone(){
+        let mut position = pt2(5000.0, -5000.0);
+        let mut heading = 1.0;
+        Boundary::Unbounded.contain(&mut position, &mut heading, &win(), 50.0);
+        assert_eq!(position, pt2(5000.0, -5000.0));
+        assert_eq!(heading, 1.0);
+    }
+}
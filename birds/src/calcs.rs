@@ -1,26 +1,33 @@
 pub use crate::bird::Bird;
 use nannou::prelude::*;
 use crate::angle;
+use crate::weighting::Weighting;
+
+pub fn is_point_nearby(point: Point2, other_point: Point2, radius: f32) -> bool{
+    let dx_2:f32 = (other_point.x - point.x).pow(2);
+    let dy_2:f32 = (other_point.y - point.y).pow(2);
+    let other_radius = (dx_2 + dy_2).sqrt();
+    other_radius <= radius
+}
 
 pub fn is_bird_nearby(bird: &Bird, other_bird: &Bird, bird_radius: f32) -> bool{
-    let dx_2:f32 = (other_bird.position().x - bird.position().x).pow(2);
-    let dy_2:f32 = (other_bird.position().y - bird.position().y).pow(2);
-    let other_bird_radius = (dx_2 + dy_2).sqrt();
-    other_bird_radius <= bird_radius
+    is_point_nearby(bird.position(), other_bird.position(), bird_radius)
 }
 
-fn average_position(bird: &Vec <Bird>) -> Point2{
+pub fn average_position<'a, I>(bird: I) -> Point2
+    where I: IntoIterator<Item = &'a Bird>
+{
     
     /* Calculate angles */
-    let num_bird = bird.len();
-    assert!(num_bird > 0);
-
+    let mut num_bird = 0;
     let mut average = pt2(0.0, 0.0);
 
-    for i in 0..num_bird{
-        average.x += bird[i].position().x;
-        average.y += bird[i].position().y;
+    for b in bird{
+        average.x += b.position().x;
+        average.y += b.position().y;
+        num_bird += 1;
     }
+    assert!(num_bird > 0);
 
     average.x /= num_bird as f32;
     average.y /= num_bird as f32;
@@ -28,19 +35,79 @@ fn average_position(bird: &Vec <Bird>) -> Point2{
     average
 }
 
-fn average_angle(bird: &Vec <Bird>) -> f32
+/* Average position of the birds, each weighted by its distance from a point.
+ * Falls back to the plain average when none of them carry any weight */
+pub fn weighted_position<'a, I>(position: Point2, bird: I, weighting: Weighting, radius: f32) -> Point2
+    where I: IntoIterator<Item = &'a Bird> + Clone
+{
+    if weighting == Weighting::Uniform{
+        return average_position(bird);
+    }
+
+    let mut total = 0.0;
+    let mut average = pt2(0.0, 0.0);
+
+    for b in bird.clone(){
+        let weight = weighting.weight(position.distance(b.position()), radius);
+        average += b.position() * weight;
+        total += weight;
+    }
+
+    if total > 0.0{
+        average / total
+    }
+    else{
+        average_position(bird)
+    }
+}
+
+/* How strongly a bird at a position reacts to the birds, the average of their strengths
+ * for the weighting. Always 1 for uniform weighting */
+pub fn strength<'a, I>(position: Point2, bird: I, weighting: Weighting, radius: f32) -> f32
+    where I: IntoIterator<Item = &'a Bird>
+{
+    if weighting == Weighting::Uniform{
+        return 1.0;
+    }
+
+    let mut total = 0.0;
+    let mut num_bird = 0;
+    for b in bird{
+        total += weighting.strength(position.distance(b.position()), radius);
+        num_bird += 1;
+    }
+
+    if num_bird > 0{
+        total / num_bird as f32
+    }
+    else{
+        1.0
+    }
+}
+
+fn average_angle<'a, I>(bird: I) -> f32
+    where I: IntoIterator<Item = &'a Bird>
+{
+    mean_heading(bird).0
+}
+
+/* Circular mean of the birds' headings, along with the length of the mean heading
+ * vector, which is 1.0 when every bird faces the same way and near 0.0 when disordered */
+pub fn mean_heading<'a, I>(bird: I) -> (f32, f32)
+    where I: IntoIterator<Item = &'a Bird>
 {
     /* Calculate angles */
-    let num_bird = bird.len();
+    let mut num_bird = 0;
 
     let mut average_sin = 0.0;
     let mut average_cos = 0.0;
 
-    for i in 0..num_bird{
-        assert!( bird[i].angle() >= 0.0);
-        assert!( bird[i].angle() < 2.0 * std::f32::consts::PI);
-        average_sin += bird[i].angle().sin();
-        average_cos += bird[i].angle().cos();
+    for b in bird{
+        assert!( b.angle() >= 0.0);
+        assert!( b.angle() < 2.0 * std::f32::consts::PI);
+        average_sin += b.angle().sin();
+        average_cos += b.angle().cos();
+        num_bird += 1;
     }
     
     average_sin /= num_bird as f32;
@@ -51,7 +118,9 @@ fn average_angle(bird: &Vec <Bird>) -> f32
     assert!(average >= -std::f32::consts::PI);
     assert!(average <= std::f32::consts::PI);
     
-    average
+    let norm = ((average_sin * average_sin) + (average_cos * average_cos)).sqrt();
+
+    (average, norm)
 }
 
 fn angle_delta(a:f32, b:f32) -> f32
@@ -59,19 +128,25 @@ fn angle_delta(a:f32, b:f32) -> f32
     angle::wrap_180(a - b)
 }
 
-pub fn separation(bird: &mut Bird, other_birds: &Vec <Bird>)->(f32, f32){
-
-    let average = average_position( other_birds );
+/* Angle away from the neighbours, their average heading and how strongly to turn */
+pub fn separation<'a, I>(bird: &Bird, other_birds: I)->(f32, f32, f32)
+    where I: IntoIterator<Item = &'a Bird> + Clone
+{
+    let weighting = bird.tuning().separation_weighting;
+    let average = weighted_position(bird.position(), other_birds.clone(), weighting, bird.separation_radius());
+    let strength = strength(bird.position(), other_birds.clone(), weighting, bird.separation_radius());
     let avg_angle = average_angle(other_birds);
     let angle = (bird.position().y - average.y).atan2(bird.position().x - average.x);
     
     assert!(angle >= -std::f32::consts::PI);
     assert!(angle <= std::f32::consts::PI);
 
-    (angle, avg_angle)
+    (angle, avg_angle, strength)
 }
 
-pub fn alignment(bird: &mut Bird, other_birds: &Vec <Bird>)->f32{ 
+pub fn alignment<'a, I>(bird: &Bird, other_birds: I)->f32
+    where I: IntoIterator<Item = &'a Bird>
+{ 
     /* Circular mean */
     let average = average_angle(other_birds);
     
@@ -87,16 +162,20 @@ pub fn alignment(bird: &mut Bird, other_birds: &Vec <Bird>)->f32{
 }
 
 
-pub fn cohesion(bird: &mut Bird, other_birds: &Vec <Bird>)->(f32,f32)
+/* Angle towards the neighbours, their average heading and how strongly to turn */
+pub fn cohesion<'a, I>(bird: &Bird, other_birds: I)->(f32, f32, f32)
+    where I: IntoIterator<Item = &'a Bird> + Clone
 {
-    let average = average_position( other_birds );
+    let weighting = bird.tuning().cohesion_weighting;
+    let average = weighted_position(bird.position(), other_birds.clone(), weighting, bird.radius());
+    let strength = strength(bird.position(), other_birds.clone(), weighting, bird.radius());
     let avg_angle = average_angle(other_birds);
     let angle = (average.y - bird.position().y).atan2(average.x - bird.position().x);
     
     assert!(angle >= -std::f32::consts::PI);
     assert!(angle <= std::f32::consts::PI);
 
-    (angle, avg_angle)
+    (angle, avg_angle, strength)
 }
 
 
@@ -107,6 +186,7 @@ mod tests {
     use crate::gain::Gain;
     use crate::proximity::ProximitySettings;
     use crate::bird::BirdConfig;
+    use crate::tuning::Tuning;
     const FLOAT_PRECISION:f32 = 0.00001;
    
     fn cmp_floats(x:f32, y:f32, precision:f32)->bool{
@@ -124,6 +204,7 @@ mod tests {
             cohesion: ProximitySettings::new(Speed::new(speed, speed, false), -rotation_angle),
             alignment_gain: Gain::new(0.0),
             speed: Speed::new(speed, speed, false),
+            tuning: Tuning::new(),
         };
 
         config
@@ -167,10 +248,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(1.0, 0.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(1.0, 0.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = separation(&mut bird, &bird_vec);
+        let angle = separation(&bird, &bird_vec);
         assert_eq!(angle.0, deg_to_rad(0.0));
     }
     
@@ -179,10 +260,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(-1.0, 0.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(-1.0, 0.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = separation(&mut bird, &bird_vec);
+        let angle = separation(&bird, &bird_vec);
         assert_eq!(angle.0, deg_to_rad(180.0));
     }
     
@@ -191,10 +272,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(0.0, 1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(0.0, 1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0),config )); 
 
-        let angle = separation(&mut bird, &bird_vec);
+        let angle = separation(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(90.0), FLOAT_PRECISION));
     }
     
@@ -203,10 +284,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(0.0, -1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(0.0, -1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = separation(&mut bird, &bird_vec);
+        let angle = separation(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(-90.0), FLOAT_PRECISION));
     }
     
@@ -215,10 +296,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(1.0, 1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(1.0, 1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = separation(&mut bird, &bird_vec);
+        let angle = separation(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(45.0), FLOAT_PRECISION));
     }
     
@@ -227,10 +308,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(-1.0, 1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(-1.0, 1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = separation(&mut bird, &bird_vec);
+        let angle = separation(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(135.0), FLOAT_PRECISION));
     }
     #[test]
@@ -238,10 +319,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(1.0, -1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(1.0, -1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = separation(&mut bird, &bird_vec);
+        let angle = separation(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(-45.0), FLOAT_PRECISION));
     }
 
@@ -250,10 +331,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(-1.0, -1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(-1.0, -1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = separation(&mut bird, &bird_vec);
+        let angle = separation(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(-135.0), FLOAT_PRECISION));
     }
     
@@ -262,10 +343,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(1.0, 0.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(1.0, 0.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = cohesion(&mut bird, &bird_vec);
+        let angle = cohesion(&bird, &bird_vec);
         assert_eq!(angle.0, std::f32::consts::PI);
     }
     
@@ -274,10 +355,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(-1.0, 0.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(-1.0, 0.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = cohesion(&mut bird, &bird_vec);
+        let angle = cohesion(&bird, &bird_vec);
         assert_eq!(angle.0, deg_to_rad(0.0));
     }
     
@@ -286,10 +367,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(0.0, 1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(0.0, 1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = cohesion(&mut bird, &bird_vec);
+        let angle = cohesion(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(-90.0), FLOAT_PRECISION));
     }
     
@@ -298,10 +379,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(0.0, -1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(0.0, -1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = cohesion(&mut bird, &bird_vec);
+        let angle = cohesion(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(90.0), FLOAT_PRECISION));
     }
     
@@ -310,10 +391,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(1.0, 1.0), deg_to_rad(0.0),config);
+        let bird = Bird::new(pt2(1.0, 1.0), deg_to_rad(0.0),config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = cohesion(&mut bird, &bird_vec);
+        let angle = cohesion(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(-135.0), FLOAT_PRECISION));
     }
     
@@ -322,10 +403,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(-1.0, 1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(-1.0, 1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = cohesion(&mut bird, &bird_vec);
+        let angle = cohesion(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(-45.0), FLOAT_PRECISION));
     }
     #[test]
@@ -333,10 +414,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(1.0, -1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(1.0, -1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = cohesion(&mut bird, &bird_vec);
+        let angle = cohesion(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(135.0), FLOAT_PRECISION));
     }
 
@@ -345,10 +426,10 @@ mod tests {
         let config = default_bird_config();
         let mut bird_vec:Vec<Bird> = Vec::new();
         
-        let mut bird = Bird::new(pt2(-1.0, -1.0), deg_to_rad(0.0), config);
+        let bird = Bird::new(pt2(-1.0, -1.0), deg_to_rad(0.0), config);
         bird_vec.push(Bird::new(pt2(0.0, 0.0), deg_to_rad(0.0), config)); 
 
-        let angle = cohesion(&mut bird, &bird_vec);
+        let angle = cohesion(&bird, &bird_vec);
         assert!(cmp_floats(angle.0, deg_to_rad(45.0), FLOAT_PRECISION));
     }
     
@@ -428,6 +509,62 @@ mod tests {
         assert!(cmp_floats(average_position.y, 0.0, FLOAT_PRECISION));
     }
     
+    #[test]
+    fn weighted_towards_nearest(){
+        let config = default_bird_config();
+        let bird_vec = vec![Bird::new(pt2(2.0, 0.0), 0.0, config), Bird::new(pt2(0.0, 20.0), 0.0, config)];
+        let origin = pt2(0.0, 0.0);
+
+        let uniform = weighted_position(origin, &bird_vec, Weighting::Uniform, 30.0);
+        assert!(cmp_floats(uniform.x, 1.0, FLOAT_PRECISION));
+        assert!(cmp_floats(uniform.y, 10.0, FLOAT_PRECISION));
+
+        /* Weights 1/2 and 1/20 */
+        let inverse = weighted_position(origin, &bird_vec, Weighting::Inverse, 30.0);
+        assert!(cmp_floats(inverse.x, 2.0 * 10.0 / 11.0, FLOAT_PRECISION));
+        assert!(cmp_floats(inverse.y, 20.0 / 11.0, FLOAT_PRECISION));
+
+        let inverse_square = weighted_position(origin, &bird_vec, Weighting::InverseSquare, 30.0);
+        assert!(inverse_square.y < inverse.y);
+
+        /* Nobody in range, plain average */
+        let linear = weighted_position(origin, &bird_vec, Weighting::Linear, 1.0);
+        assert_eq!(linear, uniform);
+    }
+
+    #[test]
+    fn weighted_separation(){
+        let mut config = default_bird_config();
+        config.tuning.separation_weighting = Weighting::Uniform;
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        let bird_vec = vec![Bird::new(pt2(2.0, 0.0), 0.0, config), Bird::new(pt2(0.0, 20.0), 0.0, config)];
+        let uniform = separation(&bird, &bird_vec);
+
+        /* The bird right alongside matters far more than the one further off */
+        config.tuning.separation_weighting = Weighting::InverseSquare;
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        let inverse_square = separation(&bird, &bird_vec);
+        assert!(inverse_square.0.abs() > uniform.0.abs());
+        assert!(inverse_square.0 < deg_to_rad(-170.0));
+    }
+
+    #[test]
+    fn nearer_turns_harder(){
+        let mut config = default_bird_config();
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        let near = vec![Bird::new(pt2(0.0, 5.0), 0.0, config)];
+        let far = vec![Bird::new(pt2(0.0, 25.0), 0.0, config)];
+
+        /* Uniform reacts the same however close */
+        assert_eq!(separation(&bird, &near).2, 1.0);
+        assert_eq!(separation(&bird, &far).2, 1.0);
+
+        config.tuning.separation_weighting = Weighting::Inverse;
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        assert!(separation(&bird, &near).2 > 1.0);
+        assert!(separation(&bird, &far).2 < 1.0);
+    }
+
     #[test]
     fn calc_angle_delta(){
 
@@ -490,5 +627,29 @@ mod tests {
         println!("{:?}", average_angle);
         assert!(cmp_floats(average_angle, deg_to_rad(90.0), FLOAT_PRECISION));
     }
-}
 
+    #[test]
+    fn mean_heading_aligned(){
+        let config = default_bird_config();
+        let bird_vec:Vec<Bird> = vec![
+            Bird::new(pt2(1.0, 2.0), deg_to_rad(30.0),config),
+            Bird::new(pt2(5.0, 2.0), deg_to_rad(30.0),config),
+        ];
+
+        let heading = mean_heading(&bird_vec);
+        assert!(cmp_floats(heading.0, deg_to_rad(30.0), FLOAT_PRECISION));
+        assert!(cmp_floats(heading.1, 1.0, FLOAT_PRECISION));
+    }
+    
+    #[test]
+    fn mean_heading_opposed(){
+        let config = default_bird_config();
+        let bird_vec:Vec<Bird> = vec![
+            Bird::new(pt2(1.0, 2.0), deg_to_rad(0.0),config),
+            Bird::new(pt2(5.0, 2.0), deg_to_rad(180.0),config),
+        ];
+
+        let heading = mean_heading(&bird_vec);
+        assert!(cmp_floats(heading.1, 0.0, FLOAT_PRECISION));
+    }
+}
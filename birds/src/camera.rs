@@ -0,0 +1,194 @@
+use nannou::prelude::*;
+
+use birds::Viewport;
+use birds::World;
+
+/* Zoom limits relative to fitting the world in the window */
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 20.0;
+
+/* Each notch of the mouse wheel zooms by this much */
+const ZOOM_STEP: f32 = 1.1;
+
+/* How quickly the camera catches up with whatever it's following, per second */
+const FOLLOW_GAIN: f32 = 2.0;
+
+/* What the camera keeps in the middle of the view */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Follow{
+    Free,
+    Centroid,
+    Bird(usize),
+}
+
+impl Follow{
+    pub fn name(&self) -> &'static str{
+        match self{
+            Follow::Free => "Free",
+            Follow::Centroid => "Centroid",
+            Follow::Bird(_) => "Selected Bird",
+        }
+    }
+}
+
+pub struct Camera{
+    centre:Point2,
+    zoom:f32,
+    follow:Follow,
+    drag:Option<Point2>,
+}
+
+impl Camera{
+    pub fn new(centre:Point2) -> Camera{
+        Camera{
+            centre,
+            zoom: 1.0,
+            follow: Follow::Free,
+            drag: None,
+        }
+    }
+
+    pub fn zoom(&self) -> f32{
+        self.zoom
+    }
+
+    pub fn follow(&self) -> Follow{
+        self.follow
+    }
+
+    /* Free, then the centroid, then the selected bird if there is one */
+    pub fn cycle_follow(&mut self, selected:Option<usize>){
+        self.follow = match (self.follow, selected){
+            (Follow::Free, _) => Follow::Centroid,
+            (Follow::Centroid, Some(bird)) => Follow::Bird(bird),
+            _ => Follow::Free,
+        };
+    }
+
+    /* Back to looking at the whole world */
+    pub fn reset(&mut self, centre:Point2, follow:Follow){
+        self.centre = centre;
+        self.zoom = 1.0;
+        self.follow = follow;
+        self.drag = None;
+    }
+
+    /* The world scaled to fit the window as it is now, then zoomed and moved */
+    pub fn viewport(&self, world:Rect<f32>, window:Rect<f32>) -> Viewport{
+        Viewport::new(world, window)
+            .zoomed(self.zoom)
+            .centred_on(self.centre)
+    }
+
+    /* Zoom by a number of wheel notches, keeping the world position under the cursor where it is */
+    pub fn zoom_at(&mut self, anchor:Point2, notches:f32){
+        let zoom = (self.zoom * ZOOM_STEP.powf(notches)).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.centre = anchor + ((self.centre - anchor) * (self.zoom / zoom));
+        self.zoom = zoom;
+    }
+
+    /* Panning takes over from following */
+    pub fn start_drag(&mut self, position:Point2){
+        self.drag = Some(position);
+        self.follow = Follow::Free;
+    }
+
+    /* Move the world along with the cursor, position being in window coordinates */
+    pub fn drag_to(&mut self, position:Point2, scale:f32){
+        if let Some(previous) = self.drag{
+            self.centre -= (position - previous) / scale;
+            self.drag = Some(position);
+        }
+    }
+
+    pub fn end_drag(&mut self){
+        self.drag = None;
+    }
+
+    pub fn dragging(&self) -> bool{
+        self.drag.is_some()
+    }
+
+    /* Ease towards whatever is being followed, giving up on birds that no longer exist */
+    pub fn update(&mut self, world:&World, dt:f32){
+        let target = match self.follow{
+            Follow::Free => return,
+            Follow::Centroid => world.metrics().centroid(),
+            Follow::Bird(bird) => match world.birds().get(bird){
+                Some(bird) => bird.position(),
+                None => {
+                    self.follow = Follow::Free;
+                    return;
+                },
+            },
+        };
+
+        let gain = (FOLLOW_GAIN * dt).min(1.0);
+        self.centre += (target - self.centre) * gain;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use birds::BirdConfig;
+
+    fn world() -> Rect<f32>{
+        Rect::from_w_h(1920.0, 1080.0)
+    }
+
+    #[test]
+    fn zoom_keeps_anchor(){
+        let mut camera = Camera::new(pt2(0.0, 0.0));
+        let anchor = pt2(400.0, -200.0);
+        let screen = camera.viewport(world(), world()).to_screen(anchor);
+
+        camera.zoom_at(anchor, 3.0);
+        assert!(camera.zoom() > 1.0);
+        assert!(camera.viewport(world(), world()).to_screen(anchor).distance(screen) < 0.01);
+
+        camera.zoom_at(anchor, -1000.0);
+        assert_eq!(camera.zoom(), MIN_ZOOM);
+    }
+
+    #[test]
+    fn drag_pans(){
+        let mut camera = Camera::new(pt2(0.0, 0.0));
+        camera.follow = Follow::Centroid;
+        camera.zoom_at(pt2(0.0, 0.0), 10.0);
+        let scale = camera.viewport(world(), world()).scale();
+
+        /* The world moves with the cursor, so the view moves the other way */
+        camera.start_drag(pt2(0.0, 0.0));
+        camera.drag_to(pt2(100.0, 0.0), scale);
+        camera.end_drag();
+        camera.drag_to(pt2(500.0, 0.0), scale);
+
+        assert_eq!(camera.follow(), Follow::Free);
+        assert!(!camera.dragging());
+        assert!((camera.viewport(world(), world()).centre().x + (100.0 / scale)).abs() < 0.001);
+    }
+
+    #[test]
+    fn follow(){
+        let mut world = World::new(world(), BirdConfig::new(), 1);
+        world.spawn_group(1, pt2(300.0, 100.0), 0.0);
+
+        let mut camera = Camera::new(pt2(0.0, 0.0));
+        camera.cycle_follow(None);
+        assert_eq!(camera.follow(), Follow::Centroid);
+        camera.cycle_follow(None);
+        assert_eq!(camera.follow(), Follow::Free);
+
+        camera.cycle_follow(Some(0));
+        camera.cycle_follow(Some(0));
+        assert_eq!(camera.follow(), Follow::Bird(0));
+        camera.update(&world, 1.0);
+        assert_eq!(camera.viewport(world.bounds(), world.bounds()).centre(), pt2(300.0, 100.0));
+
+        /* Losing the bird leaves the camera where it is */
+        camera.follow = Follow::Bird(5);
+        camera.update(&world, 1.0);
+        assert_eq!(camera.follow(), Follow::Free);
+    }
+}
@@ -0,0 +1,290 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::fs::File;
+use std::io::BufWriter;
+
+use birds::Scenario;
+use birds::Settings;
+use birds::ExportFormat;
+use birds::Exporter;
+use birds::Recorder;
+use birds::World;
+
+#[derive(Parser, Debug, Clone)]
+#[command(version, about = "Bird murmuration using Craig Reynold's Boids algorithm")]
+pub struct Cli{
+    /// Scenario file (TOML or JSON) to configure the flock from
+    pub scenario: Option<PathBuf>,
+
+    /// Number of birds, overrides the scenario
+    #[arg(short = 'n', long)]
+    pub birds: Option<u32>,
+
+    /// Random seed, overrides the scenario
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+
+    /// Simulation steps per second, overrides the scenario
+    #[arg(long, value_name = "HZ", value_parser = parse_tick_rate)]
+    pub tick_rate: Option<f32>,
+
+    /// Open in a window rather than fullscreen
+    #[arg(short, long)]
+    pub windowed: bool,
+
+    /// Window size, implies --windowed
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    pub size: Option<(u32, u32)>,
+
+    /// Start with the simulation paused
+    #[arg(short, long)]
+    pub paused: bool,
+
+    /// Start with trails shown
+    #[arg(long)]
+    pub trails: bool,
+
+    /// Start with bird radii shown
+    #[arg(long)]
+    pub radii: bool,
+
+    /// Start with the turn box shown
+    #[arg(long)]
+    pub turnbox: bool,
+
+    /// Start with the debug menu shown
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Run the simulation without opening a window, then exit
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Number of steps to run in headless mode
+    #[arg(long, value_name = "N", default_value_t = 1000, requires = "headless")]
+    pub steps: u64,
+
+    /// Number of ticks the F key skips ahead
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    pub skip: u64,
+
+    /// Seconds of simulation kept for stepping back through while paused, within a 64 MB budget
+    #[arg(long, value_name = "SECONDS", default_value_t = 5.0, value_parser = parse_rewind)]
+    pub rewind: f32,
+
+    /// Write flock metrics to a CSV or JSON lines file
+    #[arg(long, value_name = "FILE")]
+    pub export: Option<PathBuf>,
+
+    /// Export format, guessed from the file extension if not given
+    #[arg(long, value_name = "csv|jsonl", requires = "export")]
+    pub export_format: Option<ExportFormat>,
+
+    /// Export every N steps
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..), requires = "export")]
+    pub export_interval: u64,
+
+    /// Also export the position, angle and state of every bird
+    #[arg(long, requires = "export")]
+    pub export_birds: bool,
+
+    /// Record every bird's trajectory to a file for replaying later
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Play back a recording instead of running the simulation
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["headless", "record", "export"])]
+    pub replay: Option<PathBuf>,
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), String>
+{
+    let (w, h) = size.split_once('x').ok_or(format!("expected WIDTHxHEIGHT, got `{}`", size))?;
+    let w = w.trim().parse::<u32>().map_err(|err| format!("invalid width: {}", err))?;
+    let h = h.trim().parse::<u32>().map_err(|err| format!("invalid height: {}", err))?;
+
+    if w == 0 || h == 0
+    {
+        return Err(String::from("width and height must be greater than zero"));
+    }
+    Ok((w, h))
+}
+
+fn parse_tick_rate(tick_rate: &str) -> Result<f32, String>
+{
+    let tick_rate = tick_rate.trim().parse::<f32>().map_err(|err| format!("invalid tick rate: {}", err))?;
+
+    if !tick_rate.is_finite() || tick_rate <= 0.0
+    {
+        return Err(String::from("tick rate must be greater than zero"));
+    }
+    Ok(tick_rate)
+}
+
+fn parse_rewind(rewind: &str) -> Result<f32, String>
+{
+    let rewind = rewind.trim().parse::<f32>().map_err(|err| format!("invalid rewind: {}", err))?;
+
+    if !rewind.is_finite() || rewind < 0.0
+    {
+        return Err(String::from("rewind must not be negative"));
+    }
+    Ok(rewind)
+}
+
+impl Cli
+{
+    pub const DEFAULT_SIZE:(u32, u32) = (1920, 1080);
+
+    pub fn windowed(&self) -> bool
+    {
+        self.windowed || self.size.is_some()
+    }
+
+    pub fn size(&self) -> (u32, u32)
+    {
+        self.size.unwrap_or(Self::DEFAULT_SIZE)
+    }
+
+    /* The exporter asked for on the command line, if any */
+    pub fn exporter(&self) -> Option<Exporter<BufWriter<File>>>
+    {
+        let path = self.export.as_ref()?;
+        let format = self.export_format.unwrap_or(ExportFormat::from_path(path));
+
+        match Exporter::create(path, format, self.export_interval, self.export_birds)
+        {
+            Ok(exporter) => Some(exporter),
+            Err(err) => {
+                eprintln!("unable to create {}: {}", path.display(), err);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    /* The recorder asked for on the command line, if any, starting from the world as it is now */
+    pub fn recorder(&self, world: &World) -> Option<Recorder<BufWriter<File>>>
+    {
+        let path = self.record.as_ref()?;
+
+        match Recorder::create(path, world)
+        {
+            Ok(recorder) => Some(recorder),
+            Err(err) => {
+                eprintln!("unable to create {}: {}", path.display(), err);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    /* Command line options take priority over the scenario file */
+    pub fn apply(&self, scenario: &mut Scenario)
+    {
+        if let Some(birds) = self.birds
+        {
+            scenario.num_birds = birds;
+        }
+
+        if let Some(seed) = self.seed
+        {
+            scenario.seed = Some(seed);
+        }
+
+        if let Some(tick_rate) = self.tick_rate
+        {
+            scenario.tick_rate = tick_rate;
+        }
+
+        let mut settings = scenario.settings.unwrap_or(Settings{
+            show_radii: false,
+            show_turnbox: false,
+            show_trails: false,
+            show_debug: false,
+            pause: false,
+        });
+        settings.show_radii |= self.radii;
+        settings.show_turnbox |= self.turnbox;
+        settings.show_trails |= self.trails;
+        settings.show_debug |= self.debug;
+        settings.pause |= self.paused;
+        scenario.settings = Some(settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size(){
+        assert_eq!(parse_size("1280x720"), Ok((1280, 720)));
+        assert!(parse_size("1280").is_err());
+        assert!(parse_size("0x720").is_err());
+        assert!(parse_size("widexhigh").is_err());
+    }
+
+    #[test]
+    fn tick_rate(){
+        assert_eq!(parse_tick_rate("120"), Ok(120.0));
+        assert!(parse_tick_rate("0").is_err());
+        assert!(parse_tick_rate("-60").is_err());
+        assert!(parse_tick_rate("fast").is_err());
+    }
+
+    #[test]
+    fn rewind(){
+        assert_eq!(parse_rewind("2.5"), Ok(2.5));
+        assert_eq!(parse_rewind("0"), Ok(0.0));
+        assert!(parse_rewind("-1").is_err());
+        assert!(parse_rewind("inf").is_err());
+        assert!(parse_rewind("NaN").is_err());
+    }
+
+    #[test]
+    fn overrides(){
+        let cli = Cli::parse_from(["birds", "--birds", "42", "--seed", "9", "--tick-rate", "144", "--trails", "--paused"]);
+        let mut scenario = Scenario::default();
+        cli.apply(&mut scenario);
+
+        assert_eq!(scenario.num_birds, 42);
+        assert_eq!(scenario.seed, Some(9));
+        assert_eq!(scenario.tick_rate, 144.0);
+        assert!(scenario.settings.unwrap().show_trails);
+        assert!(scenario.settings.unwrap().pause);
+        assert!(!scenario.settings.unwrap().show_debug);
+    }
+
+    #[test]
+    fn steps_requires_headless(){
+        assert!(Cli::try_parse_from(["birds", "--steps", "10"]).is_err());
+
+        let cli = Cli::parse_from(["birds", "--headless", "--steps", "10"]);
+        assert_eq!(cli.steps, 10);
+    }
+
+    #[test]
+    fn export_options(){
+        assert!(Cli::try_parse_from(["birds", "--export-birds"]).is_err());
+        assert!(Cli::try_parse_from(["birds", "--export", "run.csv", "--export-interval", "0"]).is_err());
+
+        let cli = Cli::parse_from(["birds", "--export", "run.txt", "--export-format", "jsonl", "--export-interval", "5"]);
+        assert_eq!(cli.export_format, Some(ExportFormat::JsonLines));
+        assert_eq!(cli.export_interval, 5);
+    }
+
+    #[test]
+    fn size_implies_windowed(){
+        let cli = Cli::parse_from(["birds", "--size", "800x600"]);
+        assert!(cli.windowed());
+        assert_eq!(cli.size(), (800, 600));
+    }
+
+    #[test]
+    fn replay_options(){
+        assert!(Cli::try_parse_from(["birds", "--replay", "run.lloids", "--headless"]).is_err());
+        assert!(Cli::try_parse_from(["birds", "--replay", "run.lloids", "--record", "again.lloids"]).is_err());
+
+        let cli = Cli::parse_from(["birds", "--headless", "--record", "run.lloids"]);
+        assert_eq!(cli.record, Some(PathBuf::from("run.lloids")));
+    }
+}
@@ -0,0 +1,269 @@
+use nannou::prelude::*;
+
+use crate::obstacle::Obstacle;
+
+/* Shape the flock is kept inside of in place of the bounds, e.g. the outline of a reed bed */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Containment{
+    Rect{rect:Rect<f32>},
+    Circle{centre:Point2, radius:f32},
+    Ellipse{centre:Point2, radii:Vec2},
+    Polygon{points:Vec<Point2>}, // may be concave
+}
+
+/* Where a bird is relative to the edge of the containment: the heading straight back
+ * in, and how far it is past the turn offset and the hard turn offset (negative before them) */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Edge{
+    pub angle:f32,
+    pub distance:f32,
+    pub distance_hard:f32,
+}
+
+impl Containment{
+    pub fn rect(rect:Rect<f32>) -> Containment{
+        assert!(rect.w() > 0.0 && rect.h() > 0.0);
+        Containment::Rect{rect}
+    }
+
+    pub fn circle(centre:Point2, radius:f32) -> Containment{
+        assert!(radius > 0.0);
+        Containment::Circle{centre, radius}
+    }
+
+    pub fn ellipse(centre:Point2, radii:Vec2) -> Containment{
+        assert!(radii.x > 0.0 && radii.y > 0.0);
+        Containment::Ellipse{centre, radii}
+    }
+
+    pub fn polygon(points:Vec<Point2>) -> Containment{
+        assert!(Self::is_polygon(&points));
+        Containment::Polygon{points}
+    }
+
+    /* At least three points enclosing some area, either way round */
+    pub fn is_polygon(points:&[Point2]) -> bool{
+        points.len() >= 3 && Self::signed_area(points) != 0.0
+    }
+
+    /* Positive for anticlockwise points, negative for clockwise */
+    fn signed_area(points:&[Point2]) -> f32{
+        let mut area = 0.0;
+        for i in 0..points.len(){
+            area += points[i].perp_dot(points[(i + 1) % points.len()]);
+        }
+        area * 0.5
+    }
+
+    /* Distance from a point to the edge, negative inside, and the outward direction there */
+    pub fn surface(&self, position:Point2) -> (f32, Vec2){
+        match self{
+            Containment::Rect{rect} => Obstacle::Rect{rect: *rect}.surface(position),
+            Containment::Circle{centre, radius} => Obstacle::Circle{centre: *centre, radius: *radius}.surface(position),
+            Containment::Ellipse{centre, radii} => {
+                /* Not exact away from the edge, but the sign and direction always are */
+                let offset = position - *centre;
+                let gradient = offset / (*radii * *radii);
+                let k0 = (offset / *radii).length();
+                let k1 = gradient.length();
+                if k1 > 0.0{
+                    ((k0 * (k0 - 1.0)) / k1, gradient / k1)
+                }
+                else{
+                    (-radii.min_element(), vec2(1.0, 0.0))
+                }
+            },
+            Containment::Polygon{points} => {
+                let mut inside = false;
+                let mut nearest_distance = f32::MAX;
+                let mut nearest_point = points[0];
+                let mut nearest_edge = points[1] - points[0];
+
+                for i in 0..points.len(){
+                    let a = points[i];
+                    let b = points[(i + 1) % points.len()];
+                    let edge = b - a;
+
+                    /* Count the edges crossed by a ray heading off to the right */
+                    if (a.y > position.y) != (b.y > position.y){
+                        let x = a.x + (((position.y - a.y) / edge.y) * edge.x);
+                        if position.x < x{
+                            inside = !inside;
+                        }
+                    }
+
+                    let t = ((position - a).dot(edge) / edge.length_squared()).clamp(0.0, 1.0);
+                    let point = a + (edge * t);
+                    let distance = position.distance(point);
+                    if distance < nearest_distance{
+                        nearest_distance = distance;
+                        nearest_point = point;
+                        nearest_edge = edge;
+                    }
+                }
+
+                /* Right on the edge there's no offset to go by, so use the edge's normal,
+                 * which is on the right of each edge when the points run anticlockwise */
+                let outward = (position - nearest_point).normalize_or_zero();
+                if outward == Vec2::ZERO{
+                    return (0.0, vec2(nearest_edge.y, -nearest_edge.x).normalize() * Self::signed_area(points).signum());
+                }
+
+                if inside{
+                    (-nearest_distance, -outward)
+                }
+                else{
+                    (nearest_distance, outward)
+                }
+            },
+        }
+    }
+
+    /* How far into the turn offsets a point is, measured in from the edge */
+    pub fn edge(&self, position:Point2, turn_offset:f32, turn_offset_hard:f32) -> Edge{
+        let (distance, outward) = self.surface(position);
+        Edge{
+            angle: (-outward.y).atan2(-outward.x),
+            distance: distance + turn_offset,
+            distance_hard: distance + turn_offset_hard,
+        }
+    }
+
+    /* Smallest rectangle around the shape */
+    pub fn bounding_rect(&self) -> Rect<f32>{
+        match self{
+            Containment::Rect{rect} => *rect,
+            Containment::Circle{centre, radius} => Rect::from_x_y_w_h(centre.x, centre.y, radius * 2.0, radius * 2.0),
+            Containment::Ellipse{centre, radii} => Rect::from_x_y_w_h(centre.x, centre.y, radii.x * 2.0, radii.y * 2.0),
+            Containment::Polygon{points} => {
+                let min = points.iter().fold(points[0], |min, p| min.min(*p));
+                let max = points.iter().fold(points[0], |max, p| max.max(*p));
+                Rect::from_x_y_w_h((min.x + max.x) / 2.0, (min.y + max.y) / 2.0, max.x - min.x, max.y - min.y)
+            },
+        }
+    }
+
+    pub fn draw(&self, draw: &Draw)
+    {
+        let colour = rgba8(120, 120, 120, 120);
+        match self{
+            Containment::Rect{rect} => {
+                draw.rect()
+                    .xy(rect.xy())
+                    .wh(rect.wh())
+                    .no_fill()
+                    .stroke_weight(2.0)
+                    .stroke(colour);
+            },
+            Containment::Circle{centre, radius} => {
+                draw.ellipse()
+                    .xy(*centre)
+                    .w_h(radius * 2.0, radius * 2.0)
+                    .no_fill()
+                    .stroke_weight(2.0)
+                    .stroke(colour);
+            },
+            Containment::Ellipse{centre, radii} => {
+                draw.ellipse()
+                    .xy(*centre)
+                    .w_h(radii.x * 2.0, radii.y * 2.0)
+                    .no_fill()
+                    .stroke_weight(2.0)
+                    .stroke(colour);
+            },
+            Containment::Polygon{points} => {
+                draw.polyline()
+                    .weight(2.0)
+                    .points(points.iter().chain(points.first()).cloned())
+                    .color(colour);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const FLOAT_PRECISION:f32 = 0.0001;
+
+    /* A U shape, open at the top */
+    fn reed_bed() -> Containment{
+        Containment::polygon(vec![
+            pt2(-100.0, -100.0), pt2(100.0, -100.0), pt2(100.0, 100.0), pt2(50.0, 100.0),
+            pt2(50.0, 0.0), pt2(-50.0, 0.0), pt2(-50.0, 100.0), pt2(-100.0, 100.0),
+        ])
+    }
+
+    #[test]
+    fn circle_and_rect(){
+        let circle = Containment::circle(pt2(0.0, 0.0), 100.0);
+        let (distance, outward) = circle.surface(pt2(0.0, 80.0));
+        assert!((distance + 20.0).abs() < FLOAT_PRECISION);
+        assert_eq!(outward, vec2(0.0, 1.0));
+
+        let rect = Containment::rect(Rect::from_w_h(200.0, 100.0));
+        let (distance, _) = rect.surface(pt2(130.0, 0.0));
+        assert!((distance - 30.0).abs() < FLOAT_PRECISION);
+    }
+
+    #[test]
+    fn ellipse(){
+        let ellipse = Containment::ellipse(pt2(0.0, 0.0), vec2(200.0, 100.0));
+
+        let (distance, outward) = ellipse.surface(pt2(200.0, 0.0));
+        assert!(distance.abs() < FLOAT_PRECISION);
+        assert_eq!(outward, vec2(1.0, 0.0));
+
+        let (distance, outward) = ellipse.surface(pt2(0.0, 120.0));
+        assert!((distance - 20.0).abs() < FLOAT_PRECISION);
+        assert_eq!(outward, vec2(0.0, 1.0));
+
+        assert!(ellipse.surface(pt2(150.0, 50.0)).0 < 0.0);
+        assert!(ellipse.surface(pt2(150.0, 80.0)).0 > 0.0);
+    }
+
+    #[test]
+    fn concave_polygon(){
+        let reed = reed_bed();
+
+        /* In the notch is outside, nearest the inner wall */
+        let (distance, outward) = reed.surface(pt2(40.0, 50.0));
+        assert!((distance - 10.0).abs() < FLOAT_PRECISION);
+        assert_eq!(outward, vec2(-1.0, 0.0));
+
+        /* In one of the arms */
+        let (distance, outward) = reed.surface(pt2(90.0, 50.0));
+        assert!((distance + 10.0).abs() < FLOAT_PRECISION);
+        assert_eq!(outward, vec2(1.0, 0.0));
+
+        /* Same either way round */
+        let mut points = match reed{
+            Containment::Polygon{points} => points,
+            _ => unreachable!(),
+        };
+        points.reverse();
+        let reversed = Containment::polygon(points);
+        assert_eq!(reversed.surface(pt2(90.0, 50.0)), (-10.0, vec2(1.0, 0.0)));
+        assert_eq!(reversed.surface(pt2(100.0, 50.0)).1, vec2(1.0, 0.0));
+        assert_eq!(reed_bed().surface(pt2(100.0, 50.0)).1, vec2(1.0, 0.0));
+
+        assert!(!Containment::is_polygon(&[pt2(0.0, 0.0), pt2(1.0, 1.0), pt2(2.0, 2.0)]));
+    }
+
+    #[test]
+    fn edge_bands(){
+        let circle = Containment::circle(pt2(0.0, 0.0), 500.0);
+        let edge = circle.edge(pt2(400.0, 0.0), 250.0, 80.0);
+
+        /* Heading back towards the centre, 150 into the turn band and 20 short of the hard one */
+        assert!((angle_between(edge.angle, std::f32::consts::PI)).abs() < FLOAT_PRECISION);
+        assert!((edge.distance - 150.0).abs() < FLOAT_PRECISION);
+        assert!((edge.distance_hard + 20.0).abs() < FLOAT_PRECISION);
+
+        assert_eq!(reed_bed().bounding_rect(), Rect::from_w_h(200.0, 200.0));
+    }
+
+    fn angle_between(a:f32, b:f32) -> f32{
+        crate::angle::wrap_180(a - b)
+    }
+}
@@ -0,0 +1,254 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::world::World;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExportFormat{
+    Csv,
+    JsonLines,
+}
+
+impl FromStr for ExportFormat{
+    type Err = String;
+
+    fn from_str(format:&str) -> Result<ExportFormat, String>{
+        match format.to_ascii_lowercase().as_str(){
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "ndjson" | "json" => Ok(ExportFormat::JsonLines),
+            _ => Err(format!("unknown export format `{}`, expected csv or jsonl", format)),
+        }
+    }
+}
+
+impl ExportFormat{
+    /* Guess from the file extension, defaulting to CSV */
+    pub fn from_path(path:&Path) -> ExportFormat{
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ext.parse().ok())
+            .unwrap_or(ExportFormat::Csv)
+    }
+}
+
+#[derive(Serialize)]
+struct MetricsRow{
+    iteration:u64,
+    polarization:f32,
+    milling:f32,
+    nearest_neighbour:f32,
+    radius:f32,
+    centroid_x:f32,
+    centroid_y:f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    birds:Option<Vec<BirdRow>>,
+}
+
+#[derive(Serialize)]
+struct BirdRow{
+    bird:usize,
+    x:f32,
+    y:f32,
+    angle:f32,
+    state:&'static str,
+    separation_angle:f32,
+    cohesion_angle:f32,
+}
+
+/* Writes a time series of flock metrics, and optionally the state of every bird,
+ * every `interval` iterations. JSON lines puts everything for a step on one line,
+ * CSV writes the birds to a second writer as one row per bird per step */
+pub struct Exporter<W: Write>{
+    format:ExportFormat,
+    interval:u64,
+    include_birds:bool,
+    metrics:W,
+    birds:Option<W>,
+    header_written:bool,
+}
+
+impl Exporter<BufWriter<File>>{
+    /* For CSV the birds go to a sibling file, e.g. run.csv and run_birds.csv */
+    pub fn create(path:&Path, format:ExportFormat, interval:u64, include_birds:bool) -> io::Result<Exporter<BufWriter<File>>>{
+        let metrics = BufWriter::new(File::create(path)?);
+        let birds = if include_birds && format == ExportFormat::Csv{
+            Some(BufWriter::new(File::create(Self::birds_path(path))?))
+        }
+        else{
+            None
+        };
+
+        Ok(Exporter::new(format, interval, include_birds, metrics, birds))
+    }
+
+    pub fn birds_path(path:&Path) -> PathBuf{
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("export");
+        let mut name = format!("{}_birds", stem);
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()){
+            name = format!("{}.{}", name, ext);
+        }
+        path.with_file_name(name)
+    }
+}
+
+impl<W: Write> Exporter<W>{
+    pub fn new(format:ExportFormat, interval:u64, include_birds:bool, metrics:W, birds:Option<W>) -> Exporter<W>{
+        assert!(interval > 0);
+        assert!(!(include_birds && format == ExportFormat::Csv && birds.is_none()));
+
+        Exporter{
+            format,
+            interval,
+            include_birds,
+            metrics,
+            birds,
+            header_written: false,
+        }
+    }
+
+    /* Call after every step, only every `interval`th iteration is written */
+    pub fn record(&mut self, world:&World) -> io::Result<()>{
+        let iteration = world.meta().iterations();
+        if !iteration.is_multiple_of(self.interval){
+            return Ok(());
+        }
+
+        let metrics = world.metrics();
+        let mut row = MetricsRow{
+            iteration,
+            polarization: metrics.polarization(),
+            milling: metrics.milling(),
+            nearest_neighbour: metrics.nearest_neighbour(),
+            radius: metrics.radius(),
+            centroid_x: metrics.centroid().x,
+            centroid_y: metrics.centroid().y,
+            birds: None,
+        };
+
+        let birds:Vec<BirdRow> = if self.include_birds{
+            world.birds().iter().enumerate().map(|(i, b)| BirdRow{
+                bird: i,
+                x: b.position().x,
+                y: b.position().y,
+                angle: b.angle(),
+                state: b.state().name(),
+                separation_angle: b.get_separation(),
+                cohesion_angle: b.get_cohesion(),
+            }).collect()
+        }
+        else{
+            Vec::new()
+        };
+
+        match self.format{
+            ExportFormat::Csv => {
+                if !self.header_written{
+                    writeln!(self.metrics, "iteration,polarization,milling,nearest_neighbour,radius,centroid_x,centroid_y")?;
+                    if let Some(writer) = &mut self.birds{
+                        writeln!(writer, "iteration,bird,x,y,angle,state,separation_angle,cohesion_angle")?;
+                    }
+                    self.header_written = true;
+                }
+
+                writeln!(self.metrics, "{},{},{},{},{},{},{}", row.iteration, row.polarization, row.milling,
+                    row.nearest_neighbour, row.radius, row.centroid_x, row.centroid_y)?;
+
+                if let Some(writer) = &mut self.birds{
+                    for b in &birds{
+                        writeln!(writer, "{},{},{},{},{},{},{},{}", iteration, b.bird, b.x, b.y, b.angle,
+                            b.state, b.separation_angle, b.cohesion_angle)?;
+                    }
+                }
+            },
+            ExportFormat::JsonLines => {
+                if self.include_birds{
+                    row.birds = Some(birds);
+                }
+                serde_json::to_writer(&mut self.metrics, &row)?;
+                writeln!(self.metrics)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()>{
+        self.metrics.flush()?;
+        if let Some(writer) = &mut self.birds{
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::prelude::*;
+    use crate::bird::BirdConfig;
+
+    fn test_world(num_birds:u32) -> World{
+        let mut world = World::new(Rect::from_w_h(1920.0, 1080.0), BirdConfig::new(), 21);
+        let area = world.inner();
+        world.spawn(num_birds, &area);
+        world
+    }
+
+    #[test]
+    fn format_from_path(){
+        assert_eq!(ExportFormat::from_path(Path::new("run.csv")), ExportFormat::Csv);
+        assert_eq!(ExportFormat::from_path(Path::new("run.jsonl")), ExportFormat::JsonLines);
+        assert_eq!(ExportFormat::from_path(Path::new("run")), ExportFormat::Csv);
+        assert!("xml".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn birds_path(){
+        assert_eq!(Exporter::<BufWriter<File>>::birds_path(Path::new("out/run.csv")), PathBuf::from("out/run_birds.csv"));
+    }
+
+    #[test]
+    fn csv_interval(){
+        let mut world = test_world(5);
+        let mut metrics = Vec::new();
+        let mut birds = Vec::new();
+        {
+            let mut exporter = Exporter::new(ExportFormat::Csv, 10, true, &mut metrics, Some(&mut birds));
+            for _i in 0..30{
+                world.step();
+                exporter.record(&world).unwrap();
+            }
+        }
+
+        let metrics = String::from_utf8(metrics).unwrap();
+        let lines:Vec<&str> = metrics.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("iteration,"));
+        assert!(lines[1].starts_with("10,"));
+        assert!(lines[3].starts_with("30,"));
+
+        let birds = String::from_utf8(birds).unwrap();
+        assert_eq!(birds.lines().count(), 1 + (3 * 5));
+        assert!(birds.lines().nth(1).unwrap().starts_with("10,0,"));
+    }
+
+    #[test]
+    fn json_lines(){
+        let mut world = test_world(3);
+        let mut metrics:Vec<u8> = Vec::new();
+        {
+            let mut exporter = Exporter::new(ExportFormat::JsonLines, 1, true, &mut metrics, None);
+            world.step();
+            exporter.record(&world).unwrap();
+        }
+
+        let text = String::from_utf8(metrics).unwrap();
+        let value:serde_json::Value = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+        assert_eq!(value["iteration"], 1);
+        assert_eq!(value["birds"].as_array().unwrap().len(), 3);
+        assert!(value["birds"][0]["state"].is_string());
+    }
+}
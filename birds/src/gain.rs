@@ -4,7 +4,8 @@ pub struct Gain{
 }
 
 impl Gain{
-    const INC:f32 = 0.001;
+    /* Gains are per second, this is the per frame step of 0.001 at 60 frames a second */
+    const INC:f32 = 0.001 * 60.0;
     pub fn new(gain:f32) -> Gain{
         Gain{
             gain:gain,
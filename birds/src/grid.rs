@@ -0,0 +1,356 @@
+use nannou::prelude::*;
+
+use crate::bird::Bird;
+use crate::calcs;
+
+/* Uniform spatial hash over a rectangular extent, used to find neighbouring birds
+ * without comparing every bird against every other bird. Positions outside the
+ * extent are clamped into the edge cells, or wrapped round if the grid is toroidal */
+pub struct Grid{
+    extent:Rect<f32>,
+    cell_size:f32,
+    cell:Vec2,
+    cols:usize,
+    rows:usize,
+    wrap:bool,
+    cells:Vec<Vec<usize>>,
+    points:Vec<Point2>,
+}
+
+impl Grid{
+    pub fn new(extent:Rect<f32>, cell_size:f32, wrap:bool) -> Grid{
+        assert!(cell_size > 0.0);
+
+        /* Wrapped cells have to tile the extent exactly, a narrow last column would let
+         * searches across the edge fall short, so they are stretched to fit instead */
+        let (cols, rows) = if wrap{
+            (((extent.w() / cell_size).floor() as usize).max(1), ((extent.h() / cell_size).floor() as usize).max(1))
+        }
+        else{
+            (((extent.w() / cell_size).ceil() as usize).max(1), ((extent.h() / cell_size).ceil() as usize).max(1))
+        };
+        let cell = if wrap{
+            vec2(extent.w() / cols as f32, extent.h() / rows as f32)
+        }
+        else{
+            vec2(cell_size, cell_size)
+        };
+
+        Grid{
+            extent,
+            cell_size,
+            cell,
+            cols,
+            rows,
+            wrap,
+            cells: vec![Vec::new(); cols * rows],
+            points: Vec::new(),
+        }
+    }
+
+    /* Re-size the grid if the extent has changed, then re-insert every bird */
+    pub fn rebuild(&mut self, extent:Rect<f32>, cell_size:f32, wrap:bool, bird:&[Bird]){
+        if extent != self.extent || cell_size != self.cell_size || wrap != self.wrap{
+            *self = Grid::new(extent, cell_size, wrap);
+        }
+
+        for cell in &mut self.cells{
+            cell.clear();
+        }
+        self.points.clear();
+
+        for (i, b) in bird.iter().enumerate(){
+            let (col, row) = self.cell_of(b.position());
+            self.cells[(row * self.cols) + col].push(i);
+            self.points.push(b.position());
+        }
+    }
+
+    pub fn wrap(&self) -> bool{
+        self.wrap
+    }
+
+    /* Position of a point as it was when the grid was last rebuilt */
+    pub fn point(&self, index:usize) -> Point2{
+        self.points[index]
+    }
+
+    /* Indices of every point within radius of position, in ascending order */
+    pub fn query(&self, position:Point2, radius:f32, found:&mut Vec<usize>){
+        found.clear();
+
+        let span_x = (radius / self.cell.x).ceil() as i64;
+        let span_y = (radius / self.cell.y).ceil() as i64;
+        let (col, row) = self.cell_of(position);
+        let (col, row) = (col as i64, row as i64);
+
+        for dy in -span_y..=span_y{
+            for dx in -span_x..=span_x{
+                if let Some(cell) = self.cell_index(col + dx, row + dy){
+                    for &i in &self.cells[cell]{
+                        if self.is_nearby(position, self.points[i], radius){
+                            found.push(i);
+                        }
+                    }
+                }
+            }
+        }
+
+        /* Keep the same ordering as a linear scan, and remove duplicates
+         * caused by the search window wrapping onto itself */
+        found.sort_unstable();
+        if self.wrap{
+            found.dedup();
+        }
+    }
+
+    /* Distance from a point to its nearest neighbour, widening the search until one is found */
+    pub fn nearest(&self, index:usize, found:&mut Vec<usize>) -> Option<f32>{
+        let position = self.points[index];
+        let mut radius = self.cell_size;
+
+        loop{
+            self.query(position, radius, found);
+
+            let nearest = found.iter()
+                .filter(|&&i| i != index)
+                .map(|&i| self.offset(position, self.points[i]).length())
+                .fold(None, |nearest:Option<f32>, distance| Some(nearest.map_or(distance, |n| n.min(distance))));
+
+            if nearest.is_some() || found.len() >= self.points.len(){
+                return nearest;
+            }
+            radius *= 2.0;
+        }
+    }
+
+    /* Indices of the k points nearest to a point that pass the filter, not including itself,
+     * widening the search until enough are found. Ties are broken by index so the result
+     * doesn't depend on grid layout */
+    pub fn k_nearest<F>(&self, index:usize, k:usize, include:F, found:&mut Vec<usize>)
+        where F: Fn(usize) -> bool
+    {
+        let position = self.points[index];
+        let mut radius = self.cell_size;
+
+        loop{
+            self.query(position, radius, found);
+            let searched_all = found.len() >= self.points.len();
+            found.retain(|&i| i != index && include(i));
+
+            /* Everything found is within the radius, so nearer than anything still outside it */
+            if found.len() >= k || searched_all{
+                break;
+            }
+            radius *= 2.0;
+        }
+
+        found.sort_by(|&a, &b|{
+            let distance_a = self.offset(position, self.points[a]).length_squared();
+            let distance_b = self.offset(position, self.points[b]).length_squared();
+            distance_a.total_cmp(&distance_b).then(a.cmp(&b))
+        });
+        found.truncate(k);
+    }
+
+    /* Offset from position to other, taking the shortest route round the torus if wrapping */
+    pub fn offset(&self, position:Point2, other:Point2) -> Vec2{
+        let mut delta = other - position;
+        if self.wrap{
+            let w = self.extent.w();
+            let h = self.extent.h();
+            delta.x -= w * (delta.x / w).round();
+            delta.y -= h * (delta.y / h).round();
+        }
+        delta
+    }
+
+    fn is_nearby(&self, position:Point2, other:Point2, radius:f32) -> bool{
+        if self.wrap{
+            self.offset(position, other).length() <= radius
+        }
+        else{
+            calcs::is_point_nearby(position, other, radius)
+        }
+    }
+
+    fn cell_index(&self, col:i64, row:i64) -> Option<usize>{
+        let cols = self.cols as i64;
+        let rows = self.rows as i64;
+        if self.wrap{
+            let col = col.rem_euclid(cols);
+            let row = row.rem_euclid(rows);
+            Some(((row * cols) + col) as usize)
+        }
+        else if col < 0 || col >= cols || row < 0 || row >= rows{
+            None
+        }
+        else{
+            Some(((row * cols) + col) as usize)
+        }
+    }
+
+    fn cell_of(&self, position:Point2) -> (usize, usize){
+        let mut x = position.x - self.extent.left();
+        let mut y = position.y - self.extent.bottom();
+
+        if self.wrap{
+            x = x.rem_euclid(self.extent.w());
+            y = y.rem_euclid(self.extent.h());
+        }
+
+        let col = ((x / self.cell.x).floor() as i64).clamp(0, self.cols as i64 - 1);
+        let row = ((y / self.cell.y).floor() as i64).clamp(0, self.rows as i64 - 1);
+        (col as usize, row as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bird::BirdConfig;
+
+    fn birds_at(points:&[Point2]) -> Vec<Bird>{
+        let config = BirdConfig::new();
+        points.iter().map(|p| Bird::new(*p, 0.0, config)).collect()
+    }
+
+    fn brute_force(bird:&[Bird], position:Point2, radius:f32) -> Vec<usize>{
+        (0..bird.len())
+            .filter(|&i| calcs::is_point_nearby(position, bird[i].position(), radius))
+            .collect()
+    }
+
+    #[test]
+    fn matches_brute_force(){
+        let extent = Rect::from_w_h(1000.0, 600.0);
+        let mut points = Vec::new();
+        for i in 0..40{
+            for j in 0..25{
+                points.push(pt2(-520.0 + (i as f32 * 26.3), -320.0 + (j as f32 * 26.9)));
+            }
+        }
+        let bird = birds_at(&points);
+
+        let mut grid = Grid::new(extent, 100.0, false);
+        grid.rebuild(extent, 100.0, false, &bird);
+
+        let mut found = Vec::new();
+        for radius in [30.0, 100.0, 225.0]{
+            for b in &bird{
+                grid.query(b.position(), radius, &mut found);
+                assert_eq!(found, brute_force(&bird, b.position(), radius));
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_neighbour(){
+        let extent = Rect::from_w_h(1000.0, 1000.0);
+        let bird = birds_at(&[pt2(0.0, 0.0), pt2(3.0, 4.0), pt2(480.0, 480.0), pt2(-480.0, -480.0)]);
+
+        let mut grid = Grid::new(extent, 50.0, false);
+        grid.rebuild(extent, 50.0, false, &bird);
+
+        let mut found = Vec::new();
+        assert_eq!(grid.nearest(0, &mut found), Some(5.0));
+        assert_eq!(grid.nearest(1, &mut found), Some(5.0));
+
+        let far = grid.nearest(2, &mut found).unwrap();
+        assert!((far - pt2(480.0, 480.0).distance(pt2(3.0, 4.0))).abs() < 0.001);
+    }
+
+    #[test]
+    fn nearest_neighbour_alone(){
+        let extent = Rect::from_w_h(1000.0, 1000.0);
+        let bird = birds_at(&[pt2(0.0, 0.0)]);
+
+        let mut grid = Grid::new(extent, 50.0, false);
+        grid.rebuild(extent, 50.0, false, &bird);
+
+        let mut found = Vec::new();
+        assert_eq!(grid.nearest(0, &mut found), None);
+    }
+
+    #[test]
+    fn k_nearest(){
+        let extent = Rect::from_w_h(1000.0, 1000.0);
+        let bird = birds_at(&[pt2(0.0, 0.0), pt2(400.0, 0.0), pt2(0.0, 10.0), pt2(-30.0, 0.0), pt2(0.0, -400.0)]);
+
+        let mut grid = Grid::new(extent, 50.0, false);
+        grid.rebuild(extent, 50.0, false, &bird);
+
+        let mut found = Vec::new();
+        grid.k_nearest(0, 2, |_| true, &mut found);
+        assert_eq!(found, vec![2, 3]);
+
+        /* Equal distances are ordered by index */
+        grid.k_nearest(0, 3, |_| true, &mut found);
+        assert_eq!(found, vec![2, 3, 1]);
+
+        grid.k_nearest(0, 10, |_| true, &mut found);
+        assert_eq!(found, vec![2, 3, 1, 4]);
+
+        grid.k_nearest(0, 2, |i| i != 2, &mut found);
+        assert_eq!(found, vec![3, 1]);
+    }
+
+    #[test]
+    fn outside_extent_is_clamped(){
+        let extent = Rect::from_w_h(100.0, 100.0);
+        let bird = birds_at(&[pt2(60.0, 0.0), pt2(45.0, 0.0)]);
+
+        let mut grid = Grid::new(extent, 25.0, false);
+        grid.rebuild(extent, 25.0, false, &bird);
+
+        let mut found = Vec::new();
+        grid.query(pt2(45.0, 0.0), 20.0, &mut found);
+        assert_eq!(found, vec![0, 1]);
+    }
+
+    #[test]
+    fn wrapped_neighbours(){
+        let extent = Rect::from_w_h(100.0, 100.0);
+        let bird = birds_at(&[pt2(-48.0, 0.0), pt2(48.0, 0.0), pt2(0.0, 0.0)]);
+
+        let mut grid = Grid::new(extent, 25.0, true);
+        grid.rebuild(extent, 25.0, true, &bird);
+
+        let mut found = Vec::new();
+        grid.query(pt2(-48.0, 0.0), 10.0, &mut found);
+        assert_eq!(found, vec![0, 1]);
+
+        let offset = grid.offset(pt2(-48.0, 0.0), pt2(48.0, 0.0));
+        assert!((offset.x + 4.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn wrapped_uneven_extent(){
+        /* 1920 isn't a whole number of 225 cells, these two are 140 apart across the edge */
+        let extent = Rect::from_w_h(1920.0, 1080.0);
+        let bird = birds_at(&[pt2(-950.0, 0.0), pt2(830.0, 0.0), pt2(0.0, 500.0), pt2(0.0, -440.0)]);
+
+        let mut grid = Grid::new(extent, 225.0, true);
+        grid.rebuild(extent, 225.0, true, &bird);
+
+        let mut found = Vec::new();
+        grid.query(pt2(-950.0, 0.0), 225.0, &mut found);
+        assert_eq!(found, vec![0, 1]);
+
+        grid.query(pt2(0.0, 500.0), 225.0, &mut found);
+        assert_eq!(found, vec![2, 3]);
+    }
+
+    #[test]
+    fn wrapped_small_grid_no_duplicates(){
+        let extent = Rect::from_w_h(100.0, 100.0);
+        let bird = birds_at(&[pt2(0.0, 0.0), pt2(10.0, 10.0)]);
+
+        let mut grid = Grid::new(extent, 50.0, true);
+        grid.rebuild(extent, 50.0, true, &bird);
+
+        let mut found = Vec::new();
+        grid.query(pt2(0.0, 0.0), 200.0, &mut found);
+        assert_eq!(found, vec![0, 1]);
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+use crate::bird::BirdSnapshot;
+use crate::meta::Meta;
+use crate::metrics::Metrics;
+use crate::predator::Predator;
+use crate::rng::Rng;
+
+/* Everything that changes from one step to the next. Birds are kept as compact
+ * snapshots, their trails are rebuilt from the snapshots before */
+#[derive(Clone)]
+pub struct Snapshot{
+    pub(crate) bird:Vec<BirdSnapshot>,
+    pub(crate) predator:Vec<Predator>,
+    pub(crate) rng:Rng,
+    pub(crate) meta:Meta,
+    pub(crate) metrics:Metrics,
+}
+
+impl Snapshot{
+    /* Roughly how much memory the snapshot takes up */
+    pub fn size(&self) -> usize{
+        size_of::<Snapshot>()
+            + (self.bird.len() * size_of::<BirdSnapshot>())
+            + (self.predator.len() * size_of::<Predator>())
+    }
+}
+
+/* Ring buffer of the most recent snapshots with a cursor that can be moved back
+ * and forth through them. Pushing while rewound throws away the old future */
+pub struct History{
+    snapshots:VecDeque<Snapshot>,
+    capacity:usize,
+    cursor:usize,
+}
+
+impl History{
+    /* Grown as snapshots are pushed, the capacity can be far more than the memory budget allows */
+    pub fn new(capacity:usize) -> History{
+        History{
+            snapshots: VecDeque::new(),
+            capacity,
+            cursor: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize{
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize{
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.snapshots.is_empty()
+    }
+
+    /* Number of snapshots between the cursor and the latest one */
+    pub fn rewound(&self) -> usize{
+        if !self.snapshots.is_empty(){
+            self.snapshots.len() - 1 - self.cursor
+        }
+        else{
+            0
+        }
+    }
+
+    pub fn push(&mut self, snapshot:Snapshot){
+        if self.capacity == 0{
+            return;
+        }
+
+        if !self.snapshots.is_empty(){
+            self.snapshots.truncate(self.cursor + 1);
+        }
+
+        if self.snapshots.len() == self.capacity{
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+        self.cursor = self.snapshots.len() - 1;
+    }
+
+    /* Drop the oldest snapshots until no more than max are left, always keeping the latest */
+    pub fn limit(&mut self, max:usize){
+        while self.snapshots.len() > max.max(1){
+            self.snapshots.pop_front();
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+    }
+
+    /* Up to n snapshots leading up to the cursor, oldest first and ending with the one at it */
+    pub fn recent(&self, n:usize) -> impl Iterator<Item = &Snapshot>{
+        let end = (self.cursor + 1).min(self.snapshots.len());
+        self.snapshots.range(end.saturating_sub(n)..end)
+    }
+
+    pub fn back(&mut self) -> Option<&Snapshot>{
+        if self.cursor == 0{
+            return None;
+        }
+        self.cursor -= 1;
+        self.snapshots.get(self.cursor)
+    }
+
+    pub fn forward(&mut self) -> Option<&Snapshot>{
+        if self.cursor + 1 >= self.snapshots.len(){
+            return None;
+        }
+        self.cursor += 1;
+        self.snapshots.get(self.cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(iterations:u64) -> Snapshot{
+        let mut meta = Meta::new();
+        for _i in 0..iterations{
+            meta.update(1.0);
+        }
+
+        Snapshot{
+            bird: Vec::new(),
+            predator: Vec::new(),
+            rng: Rng::new(iterations),
+            meta,
+            metrics: Metrics::new(),
+        }
+    }
+
+    #[test]
+    fn drops_oldest(){
+        let mut history = History::new(3);
+        for i in 0..5{
+            history.push(snapshot(i));
+        }
+        assert_eq!(history.len(), 3);
+
+        assert_eq!(history.back().unwrap().meta.iterations(), 3);
+        assert_eq!(history.back().unwrap().meta.iterations(), 2);
+        assert!(history.back().is_none());
+        assert_eq!(history.rewound(), 2);
+
+        assert_eq!(history.forward().unwrap().meta.iterations(), 3);
+        assert_eq!(history.forward().unwrap().meta.iterations(), 4);
+        assert!(history.forward().is_none());
+        assert_eq!(history.rewound(), 0);
+    }
+
+    #[test]
+    fn push_while_rewound(){
+        let mut history = History::new(10);
+        for i in 0..5{
+            history.push(snapshot(i));
+        }
+        history.back();
+        history.back();
+        history.push(snapshot(10));
+
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.rewound(), 0);
+        assert_eq!(history.back().unwrap().meta.iterations(), 2);
+    }
+
+    #[test]
+    fn limit_and_recent(){
+        let mut history = History::new(10);
+        for i in 0..8{
+            history.push(snapshot(i));
+        }
+        history.back();
+        history.limit(5);
+        assert_eq!(history.len(), 5);
+        assert_eq!(history.rewound(), 1);
+
+        let recent:Vec<u64> = history.recent(3).map(|s| s.meta.iterations()).collect();
+        assert_eq!(recent, vec![4, 5, 6]);
+        assert_eq!(history.recent(100).count(), 4);
+
+        history.limit(0);
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn disabled(){
+        let mut history = History::new(0);
+        history.push(snapshot(1));
+        assert_eq!(history.len(), 0);
+        assert!(history.back().is_none());
+        assert!(history.forward().is_none());
+    }
+}
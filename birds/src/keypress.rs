@@ -1,7 +1,7 @@
 use nannou::prelude::*;
 
-pub use crate::settings::Settings;
-pub use crate::bird::BirdConfig;
+use birds::Settings;
+use birds::BirdConfig;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 enum SettingSelection{
@@ -32,6 +32,28 @@ enum BirdInput{
     IncrementRelease,
     DecrementPress,
     DecrementRelease,
+    SavePress,
+    SaveRelease,
+    LoadPress,
+    LoadRelease,
+    StepPress,
+    StepRelease,
+    SkipPress,
+    SkipRelease,
+    FasterPress,
+    FasterRelease,
+    SlowerPress,
+    SlowerRelease,
+    BackPress,
+    BackRelease,
+    ForwardPress,
+    ForwardRelease,
+    ModelPress,
+    ModelRelease,
+    CameraPress,
+    CameraRelease,
+    HomePress,
+    HomeRelease,
 }
 
 #[derive(Copy, Clone)]
@@ -95,6 +117,61 @@ impl KeyPress
         self.selection == SettingSelection::SpeedMax
     }
 
+    pub fn save_pressed(&self) -> bool
+    {
+        self.input == BirdInput::SavePress
+    }
+    
+    pub fn load_pressed(&self) -> bool
+    {
+        self.input == BirdInput::LoadPress
+    }
+
+    pub fn step_pressed(&self) -> bool
+    {
+        self.input == BirdInput::StepPress
+    }
+
+    pub fn skip_pressed(&self) -> bool
+    {
+        self.input == BirdInput::SkipPress
+    }
+
+    pub fn faster_pressed(&self) -> bool
+    {
+        self.input == BirdInput::FasterPress
+    }
+
+    pub fn slower_pressed(&self) -> bool
+    {
+        self.input == BirdInput::SlowerPress
+    }
+
+    pub fn back_pressed(&self) -> bool
+    {
+        self.input == BirdInput::BackPress
+    }
+
+    pub fn forward_pressed(&self) -> bool
+    {
+        self.input == BirdInput::ForwardPress
+    }
+
+    pub fn model_pressed(&self) -> bool
+    {
+        self.input == BirdInput::ModelPress
+    }
+
+    pub fn camera_pressed(&self) -> bool
+    {
+        self.input == BirdInput::CameraPress
+    }
+
+    pub fn home_pressed(&self) -> bool
+    {
+        self.input == BirdInput::HomePress
+    }
+
     pub fn handle_increment(&self, settings: &Settings, config:&mut BirdConfig)
     {
         if settings.show_debug {
@@ -153,6 +230,17 @@ impl KeyPress
             Key::Tab => self.input = BirdInput::CyclePress,
             Key::Up => self.input = BirdInput::IncrementPress,
             Key::Down => self.input = BirdInput::DecrementPress,
+            Key::S => self.input = BirdInput::SavePress,
+            Key::L => self.input = BirdInput::LoadPress,
+            Key::Period => self.input = BirdInput::StepPress,
+            Key::F => self.input = BirdInput::SkipPress,
+            Key::RBracket => self.input = BirdInput::FasterPress,
+            Key::LBracket => self.input = BirdInput::SlowerPress,
+            Key::Left => self.input = BirdInput::BackPress,
+            Key::Right => self.input = BirdInput::ForwardPress,
+            Key::M => self.input = BirdInput::ModelPress,
+            Key::C => self.input = BirdInput::CameraPress,
+            Key::H => self.input = BirdInput::HomePress,
             _ => self.input = BirdInput::Nowt,
         }
 
@@ -170,6 +258,17 @@ impl KeyPress
             Key::Tab => self.input = BirdInput::CycleRelease,
             Key::Up => self.input = BirdInput::IncrementRelease,
             Key::Down => self.input = BirdInput::DecrementRelease,
+            Key::S => self.input = BirdInput::SaveRelease,
+            Key::L => self.input = BirdInput::LoadRelease,
+            Key::Period => self.input = BirdInput::StepRelease,
+            Key::F => self.input = BirdInput::SkipRelease,
+            Key::RBracket => self.input = BirdInput::FasterRelease,
+            Key::LBracket => self.input = BirdInput::SlowerRelease,
+            Key::Left => self.input = BirdInput::BackRelease,
+            Key::Right => self.input = BirdInput::ForwardRelease,
+            Key::M => self.input = BirdInput::ModelRelease,
+            Key::C => self.input = BirdInput::CameraRelease,
+            Key::H => self.input = BirdInput::HomeRelease,
             _ => self.input = BirdInput::Nowt,
         }
 
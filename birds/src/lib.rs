@@ -0,0 +1,55 @@
+mod bird;
+pub mod calcs;
+mod angle;
+mod settings;
+mod meta;
+mod speed;
+mod proximity;
+mod gain;
+mod world;
+mod rng;
+mod grid;
+mod tuning;
+mod scenario;
+mod metrics;
+mod export;
+mod recording;
+mod history;
+mod obstacle;
+mod predator;
+mod pointer;
+mod neighbours;
+mod reynolds;
+mod weighting;
+mod boundary;
+mod containment;
+mod viewport;
+
+pub use crate::bird::Bird;
+pub use crate::bird::BirdConfig;
+pub use crate::bird::State;
+pub use crate::speed::Speed;
+pub use crate::gain::Gain;
+pub use crate::proximity::ProximitySettings;
+pub use crate::proximity::Proximity;
+
+pub use crate::settings::Settings;
+pub use crate::meta::Meta;
+pub use crate::world::World;
+pub use crate::rng::Rng;
+pub use crate::grid::Grid;
+pub use crate::tuning::Tuning;
+pub use crate::metrics::Metrics;
+pub use crate::export::{Exporter, ExportFormat};
+pub use crate::recording::{Recorder, Recording, Replay, Pose, Frame};
+pub use crate::history::{History, Snapshot};
+pub use crate::obstacle::{Obstacle, Avoidance};
+pub use crate::predator::{Predator, PredatorConfig, Pursuit};
+pub use crate::pointer::Pointer;
+pub use crate::neighbours::Neighbours;
+pub use crate::reynolds::SteeringModel;
+pub use crate::weighting::Weighting;
+pub use crate::boundary::Boundary;
+pub use crate::containment::{Containment, Edge};
+pub use crate::viewport::Viewport;
+pub use crate::scenario::{Scenario, ScenarioError, BoundsSection, ObstacleSection, PredatorSection};
@@ -1,200 +1,400 @@
 use nannou::prelude::*;
-use nannou::geom::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::fs::File;
+use std::io::BufWriter;
 
-mod bird;
-mod calcs;
-mod angle;
 mod keypress;
-mod settings;
-mod meta;
-mod speed;
-mod proximity;
-mod gain;
-
-pub use crate::bird::Bird;
-pub use crate::bird::BirdConfig;
-pub use crate::speed::Speed;
-pub use crate::gain::Gain;
-pub use crate::proximity::ProximitySettings;
-pub use crate::proximity::Proximity;
-
-//pub use crate::bird::Speed;
-pub use crate::keypress::KeyPress;
-pub use crate::settings::Settings;
-pub use crate::meta::Meta;
-
-const SCREEN_W_F32:f32 = 1920.0;
-const SCREEN_H_F32:f32 = 1080.0;
-
-const SCREEN_W_2:f32 = SCREEN_W_F32 / 2.0;
-const SCREEN_H_2:f32 = SCREEN_H_F32 / 2.0;
-
-const SCREEN_TURN_OFFSET:f32 = 250.0;
-const SCREEN_TURN_OFFSET_HARD:f32 = 80.0;
+mod cli;
+mod playback;
+mod camera;
+
+use birds::BirdConfig;
+use birds::Settings;
+use birds::Meta;
+use birds::World;
+use birds::Scenario;
+use birds::Exporter;
+use birds::Recorder;
+use birds::Recording;
+use birds::Pointer;
+use birds::Boundary;
+use birds::Viewport;
+use crate::keypress::KeyPress;
+use crate::cli::Cli;
+use crate::camera::{Camera, Follow};
+use clap::Parser;
 
+const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
-/* Bird default settings */
-const NUM_BIRDS:u32 = 150;
+/* Where the S and L keys save and load the current tuning */
+const TUNING_FILE: &str = "lloids.toml";
 
-const SPEED_GAIN:f32 = 1.4;
-const DEFAULT_BIRD_SPEED_MIN:f32 = 1.0 * SPEED_GAIN;
-const DEFAULT_BIRD_SPEED_MAX:f32 = 7.5 * SPEED_GAIN;
+/* Shift-click drops this many birds around the cursor */
+const SPAWN_GROUP: u32 = 10;
+const SPAWN_SPREAD: f32 = 30.0;
 
-const DEFAULT_SEP_SPEED_MIN:f32 = 1.25 * SPEED_GAIN;
-const DEFAULT_SEP_SPEED_MAX:f32 = 2.5 * SPEED_GAIN;
+/* Ctrl-click selects the nearest bird within this distance of the cursor, in world units */
+const SELECT_RADIUS: f32 = 20.0;
 
-const DEFAULT_COH_SPEED_MIN:f32 = 0.5 * SPEED_GAIN;
-const DEFAULT_COH_SPEED_MAX:f32 = 1.5 * SPEED_GAIN;
-    
-const DEFAULT_SEP_DELTA:f32 = 0.00625 * 2.4;
-const DEFAULT_COH_DELTA:f32 = 0.00005625 * 3.0;
-const DEFAULT_ALIGNMENT_GAIN:f32 = 0.0275;
+/* Wheel movements in pixels, e.g. from a touchpad, are counted in notches this size */
+const PIXELS_PER_NOTCH: f32 = 50.0;
 
-const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+/* nannou's model function can't capture, so the options parsed in main() are kept here */
+static CLI: OnceLock<Cli> = OnceLock::new();
+static SCENARIO: OnceLock<Scenario> = OnceLock::new();
 
 struct Model {
-    bird:Vec<Bird>,
+    world:World,
+    exporter:Option<Exporter<BufWriter<File>>>,
+    recorder:Option<Recorder<BufWriter<File>>>,
+    recorded:u64,
+    camera:Camera,
+    selected:Option<usize>,
     input:KeyPress,
     settings:Settings,
-    bird_config:BirdConfig,
-    meta:Meta,
+}
+
+/* Shared by the simulation and the replay viewer */
+fn build_window<M: 'static>(app: &App, cli: &Cli, event: fn(&App, &mut M, WindowEvent))
+{
+    let window = app.new_window()
+        .event(event);
+
+    if cli.windowed()
+    {
+        let (w, h) = cli.size();
+        window
+            .size(w, h)
+            .resizable(true)
+            .build()
+            .unwrap();
+    }
+    else
+    {
+        window
+            .decorations(false)
+            .resizable(false)
+            .fullscreen()
+            .build()
+            .unwrap();
+    }
 }
 
 fn model(app: &App) -> Model {
-    app.new_window()
-        //.size(SCREEN_W_U32, SCREEN_H_U32)
-        //.min_size(SCREEN_W_U32, SCREEN_H_U32)
-        //.max_size(SCREEN_W_U32, SCREEN_H_U32)
-        .decorations(false)
-        .resizable(false)
-        .fullscreen()
-        .event(window_event)
-        .build()
-        .unwrap();
+    let cli = CLI.get().unwrap();
+    build_window(app, cli, window_event);
+    
+    let scenario = SCENARIO.get().unwrap();
+    let mut world = scenario.build(scenario.world_bounds(), random_seed());
+    world.set_rewind((cli.rewind * world.tick_rate()) as usize);
     
     let mut model = Model {
-        bird: Vec::new(),
-        settings: Settings{
-            show_radii: false,
-            show_turnbox: false,
-            show_trails: false,
-            show_debug: false,
-            pause: false,
-        },
-        bird_config:BirdConfig{
-            separation:ProximitySettings::new(Speed::new(DEFAULT_SEP_SPEED_MIN,DEFAULT_SEP_SPEED_MAX, true), DEFAULT_SEP_DELTA),
-            cohesion:ProximitySettings::new(Speed::new(DEFAULT_COH_SPEED_MIN,DEFAULT_COH_SPEED_MAX, true), -DEFAULT_COH_DELTA),  
-            alignment_gain: Gain::new(DEFAULT_ALIGNMENT_GAIN),
-            speed: Speed::new(DEFAULT_BIRD_SPEED_MIN, DEFAULT_BIRD_SPEED_MAX, true),
-        },
+        recorder: cli.recorder(&world),
+        recorded: world.meta().iterations(),
+        camera: Camera::new(world.bounds().xy()),
+        selected: None,
+        world,
+        exporter: cli.exporter(),
+        settings: scenario.settings.unwrap(),
         input: KeyPress::new(),
-        meta: Meta::new(),
     };
+    reset_camera(&mut model);
+    model
+}
 
-    for _i in 0..NUM_BIRDS{
-        let x = random_range(-SCREEN_W_2 + SCREEN_TURN_OFFSET, SCREEN_W_2 - SCREEN_TURN_OFFSET); 
-        let y = random_range(-SCREEN_H_2 + SCREEN_TURN_OFFSET, SCREEN_H_2 - SCREEN_TURN_OFFSET); 
-        let angle = random_range(0.0, 359.0);
+/* Run the simulation as fast as possible without a window, then report on it */
+fn run_headless(cli: &Cli, scenario: &Scenario)
+{
+    let mut world = scenario.build(scenario.world_bounds(), random_seed());
+    let mut exporter = cli.exporter();
+    let mut recorder = cli.recorder(&world);
 
-        model.bird.push(Bird::new(pt2(x, y), deg_to_rad(angle),model.bird_config)); 
+    for _i in 0..cli.steps
+    {
+        world.step();
+        record(&mut exporter, &mut recorder, &world);
     }
+    flush(&mut exporter, &mut recorder);
+
+    println!("Seed: {}", world.seed());
+    println!("Birds: {}", world.birds().len());
+    println!("Iterations: {}", world.meta().iterations());
+    println!("Runtime: {:.3}s", world.meta().runtime().as_secs_f32());
+    println!("Simulated: {:.1}s at {} Hz", world.meta().simulated().as_secs_f32(), world.tick_rate());
+
+    let metrics = world.metrics();
+    println!("Polarization: {:.3}", metrics.polarization());
+    println!("Milling: {:.3}", metrics.milling());
+    println!("Nearest Neighbour: {:.1}", metrics.nearest_neighbour());
+    println!("Flock Radius: {:.1}", metrics.radius());
+}
 
-    model
+/* Seed from the clock so that each run differs, the seed is shown in the debug menu so a run can be reproduced */
+fn random_seed() -> u64
+{
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0)
 }
 
-fn window_event(_app: &App, model: &mut Model, event: WindowEvent)
+fn window_event(app: &App, model: &mut Model, event: WindowEvent)
 {
-    /* Handle keypress */
+    /* Handle keypress and mouse */
     match event{
         KeyPressed(key) => model.input.handle_press(key), 
         KeyReleased(key) => model.input.handle_release(key), 
+        MousePressed(button) => mouse_pressed(app, model, button),
+        MouseReleased(button) => mouse_released(model, button),
+        MouseMoved(position) => mouse_moved(app, model, position),
+        MouseWheel(delta, _) => mouse_wheel(app, model, delta),
         _ => {}
     }
 
     if model.input.changed(){
-        model.input.update_settings(&mut model.settings, &mut model.bird_config);
-        
-        for bird in &mut model.bird{
-            bird.refresh_settings(&mut model.bird_config);
+        let mut config = *model.world.config();
+        model.input.update_settings(&mut model.settings, &mut config);
+        model.world.set_config(config);
+
+        if model.input.save_pressed(){
+            save_tuning(model, Path::new(TUNING_FILE));
+        }
+        else if model.input.load_pressed(){
+            load_tuning(model, Path::new(TUNING_FILE));
+        }
+        else if model.input.step_pressed() && model.settings.pause{
+            step(model, 1);
+        }
+        else if model.input.back_pressed() && model.settings.pause{
+            model.world.step_back();
+        }
+        else if model.input.forward_pressed() && model.settings.pause{
+            if !model.world.step_forward(){
+                step(model, 1);
+            }
+        }
+        else if model.input.skip_pressed(){
+            step(model, CLI.get().unwrap().skip);
+        }
+        else if model.input.faster_pressed(){
+            model.world.speed_up();
+        }
+        else if model.input.slower_pressed(){
+            model.world.slow_down();
+        }
+        else if model.input.model_pressed(){
+            model.world.toggle_steering_model();
+        }
+        else if model.input.camera_pressed(){
+            model.camera.cycle_follow(selected(model));
+        }
+        else if model.input.home_pressed(){
+            reset_camera(model);
         }
         model.input.reset_latch();
     }
 }
 
-fn event(_app: &App, _model: &mut Model, _event: Event) { }
+fn save_tuning(model: &Model, path: &Path)
+{
+    let mut scenario = Scenario::from_world(&model.world);
+    scenario.settings = Some(model.settings);
 
+    match scenario.save(path)
+    {
+        Ok(()) => println!("Saved tuning to {}", path.display()),
+        Err(err) => eprintln!("{}", err),
+    }
+}
 
+/* Apply the tuning, bounds and display settings from a file to the running flock, the birds themselves are left as they are */
+fn load_tuning(model: &mut Model, path: &Path)
+{
+    match Scenario::load(path)
+    {
+        Ok(scenario) => {
+            if scenario.bounds.is_some()
+            {
+                model.world.set_bounds(scenario.world_bounds());
+            }
+            model.world.set_config(scenario.bird_config());
+            model.world.set_turn_offsets(scenario.turn_offset, scenario.turn_offset_hard);
+            model.world.set_tick_rate(scenario.tick_rate);
+            model.world.set_obstacles(scenario.obstacles.iter().map(|o| o.obstacle()).collect());
+            model.world.set_containment(scenario.containment.as_ref().map(|c| c.containment()));
+            model.world.set_neighbour_mode(scenario.neighbours);
+            model.world.set_steering_model(scenario.steering);
+            model.world.set_boundary(scenario.boundary);
+            reset_camera(model);
+            if let Some(settings) = scenario.settings
+            {
+                model.settings = settings;
+            }
+            println!("Loaded tuning from {}", path.display());
+        },
+        Err(err) => eprintln!("{}", err),
+    }
+}
 
+/* Left drag attracts the flock, right drag repels it and shift-click spawns a group.
+ * Ctrl-click selects a bird, and middle or alt-left drag pans the view */
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton)
+{
+    let position = viewport(app, model).to_world(app.mouse.position());
+    match button{
+        MouseButton::Middle => model.camera.start_drag(app.mouse.position()),
+        MouseButton::Left if app.keys.mods.alt() => model.camera.start_drag(app.mouse.position()),
+        MouseButton::Left if app.keys.mods.ctrl() => model.selected = model.world.nearest_bird(position, SELECT_RADIUS),
+        MouseButton::Left if app.keys.mods.shift() => model.world.spawn_group(SPAWN_GROUP, position, SPAWN_SPREAD),
+        MouseButton::Left => model.world.set_pointer(Some(Pointer::Attract(position))),
+        MouseButton::Right => model.world.set_pointer(Some(Pointer::Repel(position))),
+        _ => {},
+    }
+}
 
+fn mouse_released(model: &mut Model, button: MouseButton)
+{
+    if model.camera.dragging() && matches!(button, MouseButton::Middle | MouseButton::Left)
+    {
+        model.camera.end_drag();
+        return;
+    }
 
+    match (button, model.world.pointer()){
+        (MouseButton::Left, Some(Pointer::Attract(_))) => model.world.set_pointer(None),
+        (MouseButton::Right, Some(Pointer::Repel(_))) => model.world.set_pointer(None),
+        _ => {},
+    }
+}
 
-fn update(app: &App, model: &mut Model, _update: Update) { 
-    let win = app.window_rect();
+fn mouse_moved(app: &App, model: &mut Model, position: Point2)
+{
+    if model.camera.dragging()
+    {
+        let scale = viewport(app, model).scale();
+        model.camera.drag_to(position, scale);
+    }
 
-    let inner = Rect{
-        x: Range{start: win.left() + SCREEN_TURN_OFFSET, end: win.right() - SCREEN_TURN_OFFSET},
-        y: Range{start: win.bottom() + SCREEN_TURN_OFFSET, end: win.top() - SCREEN_TURN_OFFSET},
-    };
+    if let Some(pointer) = model.world.pointer()
+    {
+        let position = viewport(app, model).to_world(position);
+        model.world.set_pointer(Some(pointer.moved_to(position)));
+    }
+}
 
-    
-    let inner_hard = Rect{
-        x: Range{start: win.left() + SCREEN_TURN_OFFSET_HARD, end: win.right() - SCREEN_TURN_OFFSET_HARD},
-        y: Range{start: win.bottom() + SCREEN_TURN_OFFSET_HARD, end: win.top() - SCREEN_TURN_OFFSET_HARD},
+/* Zoom in and out around the cursor */
+fn mouse_wheel(app: &App, model: &mut Model, delta: MouseScrollDelta)
+{
+    let notches = match delta{
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_NOTCH,
     };
+    let anchor = viewport(app, model).to_world(app.mouse.position());
+    model.camera.zoom_at(anchor, notches);
+}
 
-    let num_bird = model.bird.len();
-    for i in 0..num_bird{
+fn event(_app: &App, _model: &mut Model, _event: Event) { }
 
-        /* Collect nearby birds */
-        let mut nearby:Vec<Bird> = Vec::new();
-        let mut nearby_sep:Vec<Bird> = Vec::new();
+/* The event loop never returns, so anything still buffered has to be written out here */
+fn exit(_app: &App, mut model: Model)
+{
+    flush(&mut model.exporter, &mut model.recorder);
+}
 
-        for j in 0..num_bird{
-            if i != j
-            {
-                let sep_radius = model.bird[i].separation_radius();
-                let radius = model.bird[i].radius();
-                if calcs::is_bird_nearby(&model.bird[i], &model.bird[j], sep_radius)
-                {
-                    nearby_sep.push(model.bird[j]);
-                }
-                
-                if calcs::is_bird_nearby(&model.bird[i], &model.bird[j], radius)
-                {
-                    nearby.push(model.bird[j]);
-                }
-            }
-        }
-        /* Handle Separation */
-        if nearby_sep.len() > 0{
-            let sep_angle = calcs::separation(&mut model.bird[i], &nearby_sep);
-            model.bird[i].set_separation(sep_angle.0, sep_angle.1); 
+/* Step the world straight away, regardless of the time scale or pause.
+ * Ticks that were rewound and are being run again have already been recorded */
+fn step(model: &mut Model, ticks: u64)
+{
+    for _i in 0..ticks
+    {
+        model.world.step();
+        if model.world.meta().iterations() > model.recorded
+        {
+            record(&mut model.exporter, &mut model.recorder, &model.world);
+            model.recorded = model.world.meta().iterations();
         }
-        
-        /* Handle Alignment */
-        if nearby.len() > 0 {
+    }
+}
+
+fn update(_app: &App, model: &mut Model, update: Update) { 
+    /* Step at the world's tick rate however often nannou calls in, recording every step */
+    if !model.settings.pause
+    {
+        let ticks = model.world.accumulate(update.since_last.as_secs_f32());
+        step(model, ticks as u64);
+    }
+
+    model.camera.update(&model.world, update.since_last.as_secs_f32());
+}
 
-            let align_angle = calcs::alignment(&mut model.bird[i], &nearby);
-            model.bird[i].set_alignment(align_angle); 
+/* Where the camera is looking, fitted to the window as it is now so it follows resizes */
+fn viewport(app: &App, model: &Model) -> Viewport
+{
+    model.camera.viewport(model.world.bounds(), app.window_rect())
+}
 
-            /* Handle Cohesion */
-            let coh_angle = calcs::cohesion(&mut model.bird[i], &nearby);
-            model.bird[i].set_cohesion(coh_angle.0, coh_angle.1); 
+/* Show the whole world, with no edges to keep the flock on screen the view follows it instead */
+fn reset_camera(model: &mut Model)
+{
+    let follow = match model.world.boundary(){
+        Boundary::Unbounded => Follow::Centroid,
+        _ => Follow::Free,
+    };
+    model.camera.reset(model.world.bounds().xy(), follow);
+}
+
+/* Blank out the parts of the window outside the world */
+fn draw_letterbox(draw: &Draw, viewport: &Viewport)
+{
+    for bar in viewport.bars()
+    {
+        draw.rect()
+            .xy(bar.xy())
+            .wh(bar.wh())
+            .color(BLACK);
+    }
+}
+
+/* Stop exporting or recording on the first error rather than reporting it every step */
+fn record(exporter: &mut Option<Exporter<BufWriter<File>>>, recorder: &mut Option<Recorder<BufWriter<File>>>, world: &World)
+{
+    if let Some(writer) = exporter
+    {
+        if let Err(err) = writer.record(world)
+        {
+            eprintln!("unable to write export: {}", err);
+            *exporter = None;
         }
-        else
+    }
+
+    if let Some(writer) = recorder
+    {
+        if let Err(err) = writer.record(world)
         {
-            model.bird[i].set_alignment(0.0); 
+            eprintln!("unable to write recording: {}", err);
+            *recorder = None;
         }
+    }
+}
 
-        if !model.settings.pause
+fn flush(exporter: &mut Option<Exporter<BufWriter<File>>>, recorder: &mut Option<Recorder<BufWriter<File>>>)
+{
+    if let Some(writer) = exporter
+    {
+        if let Err(err) = writer.flush()
         {
-            model.bird[i].update(&win, &inner, &inner_hard);
+            eprintln!("unable to write export: {}", err);
         }
     }
 
-    if !model.settings.pause
+    if let Some(writer) = recorder
     {
-        model.meta.update();
+        if let Err(err) = writer.flush()
+        {
+            eprintln!("unable to write recording: {}", err);
+        }
     }
 }
 
@@ -225,11 +425,42 @@ fn draw_meta(app: &App, model: &Model, config: &BirdConfig, meta: &Meta, draw: &
     draw_text(draw, 20, position, format!("Iterations: {}", meta.iterations()),false);
     position.y -= 20.0;
     draw_text(draw, 20, position, format!("Runtime: {}s", meta.runtime().as_secs()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Simulated: {:.2}s", meta.simulated().as_secs_f32()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Time Scale: {}x{}", meta.time_scale(), if model.settings.pause { " (paused)" } else { "" }),false);
+    position.y -= 20.0;
+    if model.world.rewound() > 0
+    {
+        draw_text(draw, 20, position, format!("Rewound: {} ticks", model.world.rewound()),true);
+        position.y -= 20.0;
+    }
+    draw_text(draw, 20, position, format!("Seed: {}", model.world.seed()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Tick Rate: {} Hz", model.world.tick_rate()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Neighbours: {}", model.world.neighbour_mode().name()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Steering: {}", model.world.steering_model().name()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Boundary: {}", model.world.boundary().name()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Camera: {} {:.1}x", model.camera.follow().name(), model.camera.zoom()),false);
+    position.y -= 40.0;
+
+    let metrics = model.world.metrics();
+    draw_text(draw, 20, position, format!("Polarization: {:.3}", metrics.polarization()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Milling: {:.3}", metrics.milling()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Nearest Neighbour: {:.1}", metrics.nearest_neighbour()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Flock Radius: {:.1}", metrics.radius()),false);
     position.y -= 40.0;
     
-    draw_text(draw, 20, position, format!("Separation Delta: {} rads", config.separation.delta()), model.input.separation_selected());
+    draw_text(draw, 20, position, format!("Separation Delta: {} rads/s", config.separation.delta()), model.input.separation_selected());
     position.y -= 20.0;
-    draw_text(draw, 20, position, format!("Cohesion Delta: {} rads", config.cohesion.delta()), model.input.cohesion_selected());
+    draw_text(draw, 20, position, format!("Cohesion Delta: {} rads/s", config.cohesion.delta()), model.input.cohesion_selected());
     position.y -= 20.0;
     draw_text(draw, 20, position, format!("Alignment Gain: {}", config.alignment_gain.gain()), model.input.alignment_selected());
     position.y -= 40.0; 
@@ -256,53 +487,182 @@ fn draw_meta(app: &App, model: &Model, config: &BirdConfig, meta: &Meta, draw: &
         .xy(pt2(win.right() - 125.0, win.top() -20.0));
 }
 
-fn view(app: &App, model: &Model, frame: Frame){
-    //let win = app.window_rect();
-    let draw = app.draw();
+/* Selected bird, unless it's gone since, e.g. by rewinding past when it was spawned */
+fn selected(model: &Model) -> Option<usize>
+{
+    model.selected.filter(|&i| i < model.world.birds().len())
+}
+
+/* Lines out to the birds the selected one is reacting to, separation ones in cyan */
+fn draw_neighbours(model: &Model, i: usize, nearby: &[usize], nearby_sep: &[usize], draw: &Draw)
+{
+    let position = model.world.birds()[i].position();
+    for (neighbours, colour) in [(nearby, rgba8(200, 200, 200, 80)), (nearby_sep, rgba8(0, 255, 255, 160))]
+    {
+        for &j in neighbours
+        {
+            let offset = model.world.offset(position, model.world.birds()[j].position());
+            draw.line()
+                .start(position)
+                .end(position + offset)
+                .weight(1.5)
+                .color(colour);
+        }
+    }
+}
+
+/* Everything steering the selected bird, live */
+fn draw_inspector(app: &App, model: &Model, i: usize, nearby: &[usize], nearby_sep: &[usize], draw: &Draw)
+{
     let win = app.window_rect();
-    if model.settings.show_turnbox
+    let bird = &model.world.birds()[i];
+    let mut position = pt2(win.right() - 175.0, win.top() - 60.0);
+    draw_text(draw, 20, position, format!("Bird: {}", i),true);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("State: {}", bird.state().name()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Angle: {:.1}", rad_to_deg(bird.angle())),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Align Angle: {:.1}", rad_to_deg(bird.get_alignment())),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Separation: {:.1} / {:.1}", rad_to_deg(bird.separation().angle()), rad_to_deg(bird.separation().alignment())),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Cohesion: {:.1} / {:.1}", rad_to_deg(bird.cohesion().angle()), rad_to_deg(bird.cohesion().alignment())),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Turn Angle: {:.1}/s", rad_to_deg(bird.turn_angle())),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Neighbours: {}", nearby.len()),false);
+    position.y -= 20.0;
+    draw_text(draw, 20, position, format!("Separating From: {}", nearby_sep.len()),false);
+}
+
+fn view(app: &App, model: &Model, frame: Frame){
+    let screen = app.draw();
+    let viewport = viewport(app, model);
+    let draw = viewport.transform(&screen);
+
+    /* Finding the neighbours builds a grid of its own, so only do it once a frame */
+    let inspected = selected(model).map(|i| (i, model.world.neighbours(i)));
+
+    if model.settings.show_turnbox && model.world.boundary().turns() && model.world.containment().is_none()
     {
+        let inner_hard = model.world.inner_hard();
         draw.rect()
-            .x_y(0.0, 0.0)
-            .w_h((2.0*win.right()) - (SCREEN_TURN_OFFSET_HARD * 2.0), (2.0*win.top()) - (SCREEN_TURN_OFFSET_HARD * 2.0))
+            .xy(inner_hard.xy())
+            .wh(inner_hard.wh())
             .rgba8(120, 120, 120, 16);
         
+        let inner = model.world.inner();
         draw.rect()
-            .x_y(0.0, 0.0)
-            .w_h((2.0 * win.right()) - (SCREEN_TURN_OFFSET * 2.0), (2.0 * win.top() ) - (SCREEN_TURN_OFFSET * 2.0))
+            .xy(inner.xy())
+            .wh(inner.wh())
             .rgba8(90, 90, 90, 16);
     }
+
+    if let Some(containment) = model.world.containment()
+    {
+        containment.draw(&draw);
+    }
+
+    for obstacle in model.world.obstacles(){
+        obstacle.draw(&draw);
+    }
     
     if model.settings.show_radii{
-        for bird in &model.bird{
+        for bird in model.world.birds(){
             bird.draw_region(&draw);
         }
         
-        for bird in &model.bird{
+        for bird in model.world.birds(){
             bird.draw_sep_region(&draw);
         }
     }
 
     if model.settings.show_trails{
-        for bird in &model.bird{
+        for bird in model.world.birds(){
             bird.draw_trail(&draw);
         }
     }
 
-    if model.settings.show_debug{
-        draw_meta(&app, &model, &model.bird_config, &model.meta, &draw);
+    if let Some((i, (nearby, nearby_sep))) = &inspected
+    {
+        draw_neighbours(model, *i, nearby, nearby_sep, &draw);
     }
 
-    for bird in &model.bird{
+    for bird in model.world.birds(){
         bird.draw(&draw);
     }
 
-    draw.background().color(BLACK);
-    draw.to_frame(app, &frame).unwrap();
+    if let Some(i) = selected(model)
+    {
+        model.world.birds()[i].draw_highlight(&draw);
+    }
+
+    for predator in model.world.predators(){
+        predator.draw(&draw);
+    }
+
+    if let Some(pointer) = model.world.pointer()
+    {
+        pointer.draw(&draw, model.world.config().tuning.pointer_radius);
+    }
+
+    /* Unbounded worlds go on forever, so there's nothing to box in */
+    if model.world.boundary() != Boundary::Unbounded
+    {
+        draw_letterbox(&screen, &viewport);
+    }
+
+    if model.settings.show_debug{
+        draw_meta(&app, &model, model.world.config(), model.world.meta(), &screen);
+    }
+
+    if let Some((i, (nearby, nearby_sep))) = &inspected
+    {
+        draw_inspector(app, model, *i, nearby, nearby_sep, &screen);
+    }
+
+    screen.background().color(BLACK);
+    screen.to_frame(app, &frame).unwrap();
 }
 
 fn main() {
+    let cli = Cli::parse();
+
+    let mut scenario = match &cli.scenario
+    {
+        Some(path) => Scenario::load(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }),
+        None => Scenario::default(),
+    };
+    cli.apply(&mut scenario);
+
+    if let Some(path) = &cli.replay
+    {
+        let recording = Recording::load(path).unwrap_or_else(|err| {
+            eprintln!("unable to read {}: {}", path.display(), err);
+            std::process::exit(1);
+        });
+
+        SCENARIO.set(scenario).unwrap();
+        CLI.set(cli).unwrap();
+        playback::run(recording);
+        return;
+    }
+
+    if cli.headless
+    {
+        run_headless(&cli, &scenario);
+        return;
+    }
+
+    SCENARIO.set(scenario).unwrap();
+    CLI.set(cli).unwrap();
+
     nannou::app(model)
+        .exit(exit)
         .event(event)
         .update(update)
         .view(view)
@@ -4,20 +4,29 @@ use std::time::{Instant, Duration};
 pub struct Meta{
     iterations: u64,
     runtime: Instant,
+    simulated: f64,
+    time_scale: f32,
 }
 
 impl Meta
 {
+    /* Time scales stepped through by faster() and slower() */
+    const TIME_SCALES:[f32; 9] = [0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 5.0, 7.5, 10.0];
+
     pub fn new() -> Meta{
         Meta{
             iterations: 0,
             runtime: Instant::now(),
+            simulated: 0.0,
+            time_scale: 1.0,
         }
     }
 
-    pub fn update(&mut self)
+    /* Count a tick of dt seconds of simulated time */
+    pub fn update(&mut self, dt: f32)
     {
         self.iterations += 1;
+        self.simulated += dt as f64;
     }
 
     pub fn iterations(&self) -> u64
@@ -29,4 +38,72 @@ impl Meta
     {
         self.runtime.elapsed()
     }
+
+    pub fn simulated(&self) -> Duration
+    {
+        Duration::from_secs_f64(self.simulated)
+    }
+
+    /* Simulated seconds per real second */
+    pub fn time_scale(&self) -> f32
+    {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32)
+    {
+        self.time_scale = time_scale.clamp(Self::TIME_SCALES[0], Self::TIME_SCALES[Self::TIME_SCALES.len() - 1]);
+    }
+
+    pub fn faster(&mut self)
+    {
+        if let Some(&time_scale) = Self::TIME_SCALES.iter().find(|&&scale| scale > self.time_scale)
+        {
+            self.time_scale = time_scale;
+        }
+    }
+
+    pub fn slower(&mut self)
+    {
+        if let Some(&time_scale) = Self::TIME_SCALES.iter().rev().find(|&&scale| scale < self.time_scale)
+        {
+            self.time_scale = time_scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_scale_limits(){
+        let mut meta = Meta::new();
+        for _i in 0..20{
+            meta.faster();
+        }
+        assert_eq!(meta.time_scale(), 10.0);
+
+        for _i in 0..20{
+            meta.slower();
+        }
+        assert_eq!(meta.time_scale(), 0.1);
+
+        meta.set_time_scale(100.0);
+        assert_eq!(meta.time_scale(), 10.0);
+
+        meta.set_time_scale(0.3);
+        meta.faster();
+        assert_eq!(meta.time_scale(), 0.5);
+    }
+
+    #[test]
+    fn simulated_time(){
+        let mut meta = Meta::new();
+        for _i in 0..64{
+            meta.update(1.0 / 64.0);
+        }
+        assert_eq!(meta.iterations(), 64);
+        assert_eq!(meta.simulated(), Duration::from_secs(1));
+    }
 }
@@ -0,0 +1,151 @@
+use nannou::prelude::*;
+
+use crate::bird::Bird;
+use crate::calcs;
+use crate::grid::Grid;
+
+/* Collective behaviour order parameters for the whole flock */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Metrics{
+    polarization:f32,
+    milling:f32,
+    nearest_neighbour:f32,
+    radius:f32,
+    centroid:Point2,
+}
+
+impl Metrics{
+    pub fn new() -> Metrics{
+        Metrics{
+            polarization: 0.0,
+            milling: 0.0,
+            nearest_neighbour: 0.0,
+            radius: 0.0,
+            centroid: pt2(0.0, 0.0),
+        }
+    }
+
+    /* The grid must have been built from the same birds */
+    pub fn measure(bird:&[Bird], grid:&Grid) -> Metrics{
+        let mut metrics = Metrics::new();
+        if bird.is_empty(){
+            return metrics;
+        }
+
+        metrics.centroid = calcs::average_position(bird);
+        metrics.polarization = calcs::mean_heading(bird).1;
+
+        /* Angular momentum of the unit headings about the centroid */
+        let mut momentum = 0.0;
+        for b in bird{
+            let r = grid.offset(metrics.centroid, b.position());
+            let distance = r.length();
+            if distance > 0.0{
+                let heading = vec2(b.angle().cos(), b.angle().sin());
+                momentum += ((r.x * heading.y) - (r.y * heading.x)) / distance;
+            }
+            metrics.radius = metrics.radius.max(distance);
+        }
+        metrics.milling = (momentum / bird.len() as f32).abs();
+
+        let mut found = Vec::new();
+        let mut total = 0.0;
+        let mut count = 0;
+        for i in 0..bird.len(){
+            if let Some(distance) = grid.nearest(i, &mut found){
+                total += distance;
+                count += 1;
+            }
+        }
+        if count > 0{
+            metrics.nearest_neighbour = total / count as f32;
+        }
+
+        metrics
+    }
+
+    /* 1.0 when every bird is heading the same way, towards 0.0 when disordered */
+    pub fn polarization(&self) -> f32{
+        self.polarization
+    }
+
+    /* 1.0 when the flock is circling its centroid, towards 0.0 otherwise */
+    pub fn milling(&self) -> f32{
+        self.milling
+    }
+
+    /* Mean distance from each bird to its nearest neighbour */
+    pub fn nearest_neighbour(&self) -> f32{
+        self.nearest_neighbour
+    }
+
+    /* Distance from the centroid to the furthest bird */
+    pub fn radius(&self) -> f32{
+        self.radius
+    }
+
+    pub fn centroid(&self) -> Point2{
+        self.centroid
+    }
+}
+
+impl Default for Metrics{
+    fn default() -> Metrics{
+        Metrics::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bird::BirdConfig;
+    use crate::angle;
+    const FLOAT_PRECISION:f32 = 0.0001;
+
+    fn cmp_floats(x:f32, y:f32, precision:f32)->bool{
+        let delta = (x - y).abs();
+        delta <= precision
+    }
+
+    fn measure(bird:&[Bird]) -> Metrics{
+        let extent = Rect::from_w_h(1000.0, 1000.0);
+        let mut grid = Grid::new(extent, 100.0, false);
+        grid.rebuild(extent, 100.0, false, bird);
+        Metrics::measure(bird, &grid)
+    }
+
+    #[test]
+    fn empty_flock(){
+        assert_eq!(measure(&[]), Metrics::new());
+    }
+
+    #[test]
+    fn aligned_flock(){
+        let config = BirdConfig::new();
+        let bird:Vec<Bird> = (0..10).map(|i| Bird::new(pt2(i as f32 * 10.0, 0.0), deg_to_rad(45.0), config)).collect();
+
+        let metrics = measure(&bird);
+        assert!(cmp_floats(metrics.polarization(), 1.0, FLOAT_PRECISION));
+        assert!(cmp_floats(metrics.nearest_neighbour(), 10.0, FLOAT_PRECISION));
+        assert!(cmp_floats(metrics.centroid().x, 45.0, FLOAT_PRECISION));
+        assert!(cmp_floats(metrics.radius(), 45.0, FLOAT_PRECISION));
+    }
+
+    #[test]
+    fn milling_flock(){
+        let config = BirdConfig::new();
+        let num_bird = 36;
+
+        /* Birds on a circle, each heading anticlockwise along it */
+        let bird:Vec<Bird> = (0..num_bird).map(|i|{
+            let theta = deg_to_rad(i as f32 * 10.0);
+            let heading = angle::wrap(theta + (std::f32::consts::PI / 2.0));
+            Bird::new(pt2(200.0 * theta.cos(), 200.0 * theta.sin()), heading, config)
+        }).collect();
+
+        let metrics = measure(&bird);
+        assert!(cmp_floats(metrics.milling(), 1.0, FLOAT_PRECISION));
+        assert!(cmp_floats(metrics.polarization(), 0.0, FLOAT_PRECISION));
+        assert!(cmp_floats(metrics.radius(), 200.0, 0.01));
+    }
+}
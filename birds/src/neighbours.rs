@@ -0,0 +1,203 @@
+use nannou::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::bird::Bird;
+use crate::grid::Grid;
+
+/* How a bird picks the neighbours it reacts to */
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "mode", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Neighbours{
+    /* Everyone within the region radius */
+    #[default]
+    Metric,
+    /* The k nearest, however far away they are */
+    Topological{k:usize},
+    /* Those sharing an edge of the bird's Voronoi cell, within the region radius */
+    Voronoi,
+}
+
+impl Neighbours{
+    /* Starlings keep track of six or seven neighbours */
+    pub const K:usize = 7;
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            Neighbours::Metric => "Metric",
+            Neighbours::Topological{..} => "Topological",
+            Neighbours::Voronoi => "Voronoi",
+        }
+    }
+
+    /* Fill nearby with the neighbours bird i aligns and coheres with, and nearby_sep with
+     * those it separates from. Only birds inside its field of view count, and both lists
+     * come out in ascending order. The grid must have been built from the same birds */
+    pub fn select(&self, grid:&Grid, bird:&[Bird], i:usize, nearby:&mut Vec<usize>, nearby_sep:&mut Vec<usize>){
+        let position = grid.point(i);
+        let sees = |j:usize, field_of_view:f32| j != i && bird[i].sees(grid.offset(position, grid.point(j)), field_of_view);
+
+        /* Separation is always by distance, it's about not flying into each other */
+        grid.query(position, bird[i].separation_radius(), nearby_sep);
+        nearby_sep.retain(|&j| sees(j, bird[i].separation_field_of_view()));
+
+        let field_of_view = bird[i].field_of_view();
+        match self{
+            Neighbours::Metric => {
+                grid.query(position, bird[i].radius(), nearby);
+                nearby.retain(|&j| sees(j, field_of_view));
+            },
+            Neighbours::Topological{k} => {
+                grid.k_nearest(i, *k, |j| sees(j, field_of_view), nearby);
+                nearby.sort_unstable();
+            },
+            Neighbours::Voronoi => {
+                grid.query(position, bird[i].radius(), nearby);
+                nearby.retain(|&j| sees(j, field_of_view));
+                voronoi(grid, i, bird[i].radius(), nearby);
+                nearby.sort_unstable();
+            },
+        }
+    }
+}
+
+/* Keep only the candidates whose perpendicular bisector with bird i forms an edge of its
+ * Voronoi cell. The cell starts as a square the size of the search area and is clipped
+ * by each bisector in turn, nearest first. Each edge remembers which bird made it */
+fn voronoi(grid:&Grid, i:usize, radius:f32, candidates:&mut Vec<usize>){
+    let position = grid.point(i);
+    let mut offsets:Vec<(usize, Vec2)> = candidates.iter()
+        .map(|&j| (j, grid.offset(position, grid.point(j))))
+        .filter(|(_, offset)| offset.length_squared() > 0.0)
+        .collect();
+    offsets.sort_by(|a, b| a.1.length_squared().total_cmp(&b.1.length_squared()));
+
+    /* Relative to bird i, vertex k is the start of an edge running to vertex k + 1 */
+    let mut cell:Vec<(Vec2, Option<usize>)> = vec![
+        (vec2(-radius, -radius), None),
+        (vec2(radius, -radius), None),
+        (vec2(radius, radius), None),
+        (vec2(-radius, radius), None),
+    ];
+    let mut clipped:Vec<(Vec2, Option<usize>)> = Vec::with_capacity(cell.len() + 1);
+
+    for &(j, offset) in &offsets{
+        /* A bisector further out than the furthest corner can't touch the cell */
+        let reach = cell.iter().map(|(v, _)| v.length_squared()).fold(0.0, f32::max);
+        if offset.length_squared() * 0.25 >= reach{
+            break;
+        }
+
+        let midpoint = offset * 0.5;
+        let inside = |v:Vec2| (v - midpoint).dot(offset) <= 0.0;
+
+        clipped.clear();
+        for k in 0..cell.len(){
+            let (a, edge) = cell[k];
+            let (b, _) = cell[(k + 1) % cell.len()];
+
+            if inside(a){
+                clipped.push((a, edge));
+                if !inside(b){
+                    clipped.push((intersect(a, b, midpoint, offset), Some(j)));
+                }
+            }
+            else if inside(b){
+                clipped.push((intersect(a, b, midpoint, offset), edge));
+            }
+        }
+        std::mem::swap(&mut cell, &mut clipped);
+    }
+
+    candidates.clear();
+    for k in 0..cell.len(){
+        let (a, edge) = cell[k];
+        let (b, _) = cell[(k + 1) % cell.len()];
+        if let Some(j) = edge{
+            if a.distance_squared(b) > f32::EPSILON && !candidates.contains(&j){
+                candidates.push(j);
+            }
+        }
+    }
+}
+
+/* Where the edge from a to b crosses the line through midpoint perpendicular to normal */
+fn intersect(a:Vec2, b:Vec2, midpoint:Vec2, normal:Vec2) -> Vec2{
+    let da = (a - midpoint).dot(normal);
+    let db = (b - midpoint).dot(normal);
+    a + ((b - a) * (da / (da - db)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bird::BirdConfig;
+
+    fn birds_at(points:&[Point2], config:BirdConfig) -> (Vec<Bird>, Grid){
+        let bird:Vec<Bird> = points.iter().map(|p| Bird::new(*p, 0.0, config)).collect();
+        let extent = Rect::from_w_h(2000.0, 2000.0);
+        let mut grid = Grid::new(extent, 100.0, false);
+        grid.rebuild(extent, 100.0, false, &bird);
+        (bird, grid)
+    }
+
+    fn select(neighbours:Neighbours, points:&[Point2], config:BirdConfig) -> (Vec<usize>, Vec<usize>){
+        let (bird, grid) = birds_at(points, config);
+        let mut nearby = Vec::new();
+        let mut nearby_sep = Vec::new();
+        neighbours.select(&grid, &bird, 0, &mut nearby, &mut nearby_sep);
+        (nearby, nearby_sep)
+    }
+
+    #[test]
+    fn metric(){
+        let points = [pt2(0.0, 0.0), pt2(20.0, 0.0), pt2(200.0, 0.0), pt2(500.0, 0.0)];
+        assert_eq!(select(Neighbours::Metric, &points, BirdConfig::new()), (vec![1, 2], vec![1]));
+    }
+
+    #[test]
+    fn topological(){
+        let points = [pt2(0.0, 0.0), pt2(900.0, 0.0), pt2(20.0, 0.0), pt2(-600.0, 0.0), pt2(300.0, 0.0)];
+        let config = BirdConfig::new();
+
+        /* Distance doesn't matter, only the number of neighbours */
+        assert_eq!(select(Neighbours::Topological{k: 2}, &points, config), (vec![2, 4], vec![2]));
+        assert_eq!(select(Neighbours::Topological{k: 3}, &points, config), (vec![2, 3, 4], vec![2]));
+        assert_eq!(select(Neighbours::Topological{k: 10}, &points, config), (vec![1, 2, 3, 4], vec![2]));
+    }
+
+    #[test]
+    fn voronoi_hides_birds_behind_others(){
+        /* Bird 2 is straight behind bird 1, so it's cut off from bird 0's cell */
+        let points = [pt2(0.0, 0.0), pt2(50.0, 0.0), pt2(150.0, 0.0), pt2(0.0, 60.0), pt2(-40.0, -40.0)];
+        assert_eq!(select(Neighbours::Voronoi, &points, BirdConfig::new()), (vec![1, 3, 4], vec![]));
+    }
+
+    #[test]
+    fn blind_spot(){
+        /* Bird 0 faces east, so birds 2 and 4 are behind it */
+        let points = [pt2(0.0, 0.0), pt2(20.0, 5.0), pt2(-20.0, 0.0), pt2(150.0, 0.0), pt2(-150.0, 10.0)];
+        let mut config = BirdConfig::new();
+        config.tuning.field_of_view = 270.0;
+        config.tuning.separation_field_of_view = 270.0;
+
+        assert_eq!(select(Neighbours::Metric, &points, BirdConfig::new()), (vec![1, 2, 3, 4], vec![1, 2]));
+        assert_eq!(select(Neighbours::Metric, &points, config), (vec![1, 3], vec![1]));
+        assert_eq!(select(Neighbours::Topological{k: 2}, &points, config), (vec![1, 3], vec![1]));
+
+        /* The cones are separate, so a bird behind can still be separated from */
+        config.tuning.separation_field_of_view = 360.0;
+        assert_eq!(select(Neighbours::Metric, &points, config), (vec![1, 3], vec![1, 2]));
+    }
+
+    #[test]
+    fn voronoi_ring(){
+        /* Every bird in a ring round the centre is a neighbour of it */
+        let mut points = vec![pt2(0.0, 0.0)];
+        for k in 0..8{
+            let angle = k as f32 * std::f32::consts::PI / 4.0;
+            points.push(pt2(100.0 * angle.cos(), 100.0 * angle.sin()));
+        }
+        points.push(pt2(200.0, 0.0));
+        assert_eq!(select(Neighbours::Voronoi, &points, BirdConfig::new()).0, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
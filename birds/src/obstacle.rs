@@ -0,0 +1,251 @@
+use nannou::prelude::*;
+
+/* Static shapes in the world that birds steer around */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Obstacle{
+    Circle{centre:Point2, radius:f32},
+    Rect{rect:Rect<f32>},
+    Polygon{points:Vec<Point2>}, // convex, anticlockwise
+}
+
+/* Where the nearest obstacle is relative to a bird: the direction pointing out of
+ * the obstacle towards the bird, and how far away its surface is (negative inside) */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Avoidance{
+    pub angle:f32,
+    pub distance:f32,
+}
+
+impl Obstacle{
+    pub fn circle(centre:Point2, radius:f32) -> Obstacle{
+        assert!(radius > 0.0);
+        Obstacle::Circle{centre, radius}
+    }
+
+    pub fn rect(rect:Rect<f32>) -> Obstacle{
+        assert!(rect.w() > 0.0 && rect.h() > 0.0);
+        Obstacle::Rect{rect}
+    }
+
+    /* Points can be given either way round, they are stored anticlockwise */
+    pub fn polygon(points:Vec<Point2>) -> Obstacle{
+        assert!(Self::is_convex(&points));
+        let mut points = points;
+        if Self::signed_area(&points) < 0.0{
+            points.reverse();
+        }
+        Obstacle::Polygon{points}
+    }
+
+    pub fn is_convex(points:&[Point2]) -> bool{
+        if points.len() < 3{
+            return false;
+        }
+
+        let mut sign = 0.0;
+        for i in 0..points.len(){
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            let c = points[(i + 2) % points.len()];
+            let cross = (b - a).perp_dot(c - b);
+
+            if cross != 0.0{
+                if sign != 0.0 && cross.signum() != sign{
+                    return false;
+                }
+                sign = cross.signum();
+            }
+        }
+        sign != 0.0
+    }
+
+    fn signed_area(points:&[Point2]) -> f32{
+        let mut area = 0.0;
+        for i in 0..points.len(){
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            area += a.perp_dot(b);
+        }
+        area * 0.5
+    }
+
+    /* Distance from a point to the surface, negative inside, and the outward direction at the nearest point */
+    pub fn surface(&self, position:Point2) -> (f32, Vec2){
+        match self{
+            Obstacle::Circle{centre, radius} => {
+                let offset = position - *centre;
+                (offset.length() - radius, Self::direction(offset))
+            },
+            Obstacle::Rect{rect} => {
+                let offset = position - rect.xy();
+                let half = rect.wh() * 0.5;
+                let outside = vec2(offset.x.abs() - half.x, offset.y.abs() - half.y);
+
+                if outside.x > 0.0 || outside.y > 0.0{
+                    let nearest = vec2(offset.x.clamp(-half.x, half.x), offset.y.clamp(-half.y, half.y));
+                    let away = offset - nearest;
+                    (away.length(), Self::direction(away))
+                }
+                else if outside.x > outside.y{
+                    (outside.x, vec2(offset.x.signum(), 0.0))
+                }
+                else{
+                    (outside.y, vec2(0.0, offset.y.signum()))
+                }
+            },
+            Obstacle::Polygon{points} => {
+                let mut inside = true;
+                let mut nearest_distance = f32::MAX;
+                let mut nearest_point = points[0];
+                let mut nearest_normal = vec2(1.0, 0.0);
+
+                for i in 0..points.len(){
+                    let a = points[i];
+                    let b = points[(i + 1) % points.len()];
+                    let edge = b - a;
+
+                    /* Anticlockwise, so the outward normal is on the right of each edge */
+                    if edge.perp_dot(position - a) < 0.0{
+                        inside = false;
+                    }
+
+                    let t = ((position - a).dot(edge) / edge.length_squared()).clamp(0.0, 1.0);
+                    let point = a + (edge * t);
+                    let distance = position.distance(point);
+                    if distance < nearest_distance{
+                        nearest_distance = distance;
+                        nearest_point = point;
+                        nearest_normal = Self::direction(vec2(edge.y, -edge.x));
+                    }
+                }
+
+                if inside{
+                    (-nearest_distance, nearest_normal)
+                }
+                else{
+                    (nearest_distance, Self::direction(position - nearest_point))
+                }
+            },
+        }
+    }
+
+    /* Nearest obstacle within range of a point, if any */
+    pub fn nearest(obstacles:&[Obstacle], position:Point2, range:f32) -> Option<Avoidance>{
+        let mut nearest:Option<Avoidance> = None;
+        for obstacle in obstacles{
+            let (distance, normal) = obstacle.surface(position);
+            if distance < range && nearest.is_none_or(|n| distance < n.distance){
+                nearest = Some(Avoidance{
+                    angle: normal.y.atan2(normal.x),
+                    distance,
+                });
+            }
+        }
+        nearest
+    }
+
+    fn direction(offset:Vec2) -> Vec2{
+        let direction = offset.normalize_or_zero();
+        if direction == Vec2::ZERO{
+            vec2(1.0, 0.0)
+        }
+        else{
+            direction
+        }
+    }
+
+    pub fn draw(&self, draw: &Draw)
+    {
+        match self{
+            Obstacle::Circle{centre, radius} => {
+                draw.ellipse()
+                    .xy(*centre)
+                    .w_h(radius * 2.0, radius * 2.0)
+                    .rgb8(60, 60, 70);
+            },
+            Obstacle::Rect{rect} => {
+                draw.rect()
+                    .xy(rect.xy())
+                    .wh(rect.wh())
+                    .rgb8(60, 60, 70);
+            },
+            Obstacle::Polygon{points} => {
+                draw.polygon()
+                    .points(points.iter().cloned())
+                    .rgb8(60, 60, 70);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const FLOAT_PRECISION:f32 = 0.0001;
+
+    fn square() -> Vec<Point2>{
+        vec![pt2(-10.0, -10.0), pt2(10.0, -10.0), pt2(10.0, 10.0), pt2(-10.0, 10.0)]
+    }
+
+    #[test]
+    fn circle_surface(){
+        let circle = Obstacle::circle(pt2(10.0, 0.0), 5.0);
+
+        let (distance, normal) = circle.surface(pt2(30.0, 0.0));
+        assert!((distance - 15.0).abs() < FLOAT_PRECISION);
+        assert_eq!(normal, vec2(1.0, 0.0));
+
+        let (distance, normal) = circle.surface(pt2(10.0, 2.0));
+        assert!((distance + 3.0).abs() < FLOAT_PRECISION);
+        assert_eq!(normal, vec2(0.0, 1.0));
+    }
+
+    #[test]
+    fn rect_surface(){
+        let rect = Obstacle::rect(Rect::from_w_h(20.0, 10.0));
+
+        let (distance, normal) = rect.surface(pt2(0.0, 15.0));
+        assert!((distance - 10.0).abs() < FLOAT_PRECISION);
+        assert_eq!(normal, vec2(0.0, 1.0));
+
+        let (distance, _) = rect.surface(pt2(13.0, 9.0));
+        assert!((distance - 5.0).abs() < FLOAT_PRECISION);
+
+        let (distance, normal) = rect.surface(pt2(-8.0, 1.0));
+        assert!((distance + 2.0).abs() < FLOAT_PRECISION);
+        assert_eq!(normal, vec2(-1.0, 0.0));
+    }
+
+    #[test]
+    fn polygon_matches_rect(){
+        let rect = Obstacle::rect(Rect::from_w_h(20.0, 20.0));
+        let mut points = square();
+        points.reverse();
+        let polygon = Obstacle::polygon(points);
+
+        for position in [pt2(0.0, 15.0), pt2(14.0, -13.0), pt2(-25.0, 3.0), pt2(4.0, 7.0), pt2(-9.0, 0.0)]{
+            let (rect_distance, rect_normal) = rect.surface(position);
+            let (polygon_distance, polygon_normal) = polygon.surface(position);
+            assert!((rect_distance - polygon_distance).abs() < FLOAT_PRECISION);
+            assert!((rect_normal - polygon_normal).length() < FLOAT_PRECISION);
+        }
+    }
+
+    #[test]
+    fn convex(){
+        assert!(Obstacle::is_convex(&square()));
+        assert!(!Obstacle::is_convex(&[pt2(0.0, 0.0), pt2(1.0, 1.0)]));
+        assert!(!Obstacle::is_convex(&[pt2(0.0, 0.0), pt2(10.0, 0.0), pt2(2.0, 2.0), pt2(0.0, 10.0)]));
+    }
+
+    #[test]
+    fn nearest_in_range(){
+        let obstacles = vec![Obstacle::circle(pt2(0.0, 0.0), 10.0), Obstacle::circle(pt2(50.0, 0.0), 10.0)];
+
+        let avoidance = Obstacle::nearest(&obstacles, pt2(35.0, 0.0), 20.0).unwrap();
+        assert!((avoidance.distance - 5.0).abs() < FLOAT_PRECISION);
+        assert!((avoidance.angle - std::f32::consts::PI).abs() < FLOAT_PRECISION);
+
+        assert!(Obstacle::nearest(&obstacles, pt2(25.0, 40.0), 20.0).is_none());
+    }
+}
@@ -0,0 +1,169 @@
+use nannou::prelude::*;
+use std::sync::Mutex;
+
+use birds::Recording;
+use birds::Replay;
+use birds::Settings;
+use birds::Viewport;
+use crate::keypress::KeyPress;
+use crate::{CLI, SCENARIO};
+
+/* Page Up and Page Down skip this many frames */
+const SKIP_FRAMES:i64 = 300;
+
+/* The recording is handed over to the model function, which can't capture */
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+
+struct Playback {
+    replay:Replay,
+    bounds:Rect<f32>,
+    inner:Rect<f32>,
+    inner_hard:Rect<f32>,
+    input:KeyPress,
+    settings:Settings,
+}
+
+fn model(app: &App) -> Playback {
+    crate::build_window(app, CLI.get().unwrap(), window_event);
+
+    let scenario = SCENARIO.get().unwrap();
+    let recording = RECORDING.lock().unwrap().take().unwrap();
+    let bounds = recording.bounds();
+
+    Playback {
+        replay: Replay::new(recording, scenario.bird_config()),
+        bounds,
+        inner: bounds.pad(scenario.turn_offset),
+        inner_hard: bounds.pad(scenario.turn_offset_hard),
+        input: KeyPress::new(),
+        settings: scenario.settings.unwrap(),
+    }
+}
+
+fn window_event(_app: &App, model: &mut Playback, event: WindowEvent)
+{
+    /* Scrubbing and speed are handled here, the overlay toggles are shared with the simulation */
+    match event{
+        KeyPressed(Key::Left) => model.replay.scrub(-1),
+        KeyPressed(Key::Right) => model.replay.scrub(1),
+        KeyPressed(Key::PageDown) => model.replay.scrub(-SKIP_FRAMES),
+        KeyPressed(Key::PageUp) => model.replay.scrub(SKIP_FRAMES),
+        KeyPressed(Key::Up) => model.replay.faster(),
+        KeyPressed(Key::Down) => model.replay.slower(),
+        KeyPressed(Key::Home) => model.replay.seek(0),
+        KeyPressed(key) => model.input.handle_press(key),
+        KeyReleased(key) => model.input.handle_release(key),
+        _ => {}
+    }
+
+    if model.input.changed(){
+        let mut config = SCENARIO.get().unwrap().bird_config();
+        model.input.update_settings(&mut model.settings, &mut config);
+        model.input.reset_latch();
+    }
+}
+
+fn update(_app: &App, model: &mut Playback, update: Update) {
+    if !model.settings.pause
+    {
+        /* Recordings hold one frame per tick, so 1x plays back at the recorded tick rate */
+        let tick_rate = model.replay.recording().tick_rate();
+        model.replay.advance(update.since_last.as_secs_f32() * tick_rate);
+    }
+}
+
+fn draw_meta(app: &App, model: &Playback, draw: &Draw)
+{
+    let win = app.window_rect();
+    let recording = model.replay.recording();
+    let mut position = pt2(win.left() + 125.0, win.top() - 20.0);
+    crate::draw_text(draw, 20, position, format!("Frame: {} / {}", model.replay.frame(), recording.len() - 1), false);
+    position.y -= 20.0;
+    crate::draw_text(draw, 20, position, format!("Speed: {}x", model.replay.speed()), false);
+    position.y -= 20.0;
+    crate::draw_text(draw, 20, position, format!("Seed: {}", recording.seed()), false);
+    position.y -= 20.0;
+    crate::draw_text(draw, 20, position, format!("Birds: {}", model.replay.birds().len()), false);
+    position.y -= 40.0;
+
+    if model.settings.pause
+    {
+        crate::draw_text(draw, 20, position, String::from("Paused"), true);
+    }
+    else if model.replay.finished()
+    {
+        crate::draw_text(draw, 20, position, String::from("Finished"), true);
+    }
+}
+
+fn view(app: &App, model: &Playback, frame: Frame){
+    let screen = app.draw();
+    let viewport = Viewport::new(model.bounds, app.window_rect());
+    let draw = viewport.transform(&screen);
+    let recording = model.replay.recording();
+    if model.settings.show_turnbox && recording.containment().is_none()
+    {
+        draw.rect()
+            .xy(model.inner_hard.xy())
+            .wh(model.inner_hard.wh())
+            .rgba8(120, 120, 120, 16);
+
+        draw.rect()
+            .xy(model.inner.xy())
+            .wh(model.inner.wh())
+            .rgba8(90, 90, 90, 16);
+    }
+
+    if let Some(containment) = recording.containment()
+    {
+        containment.draw(&draw);
+    }
+
+    for obstacle in recording.obstacles(){
+        obstacle.draw(&draw);
+    }
+
+    if model.settings.show_radii{
+        for bird in model.replay.birds(){
+            bird.draw_region(&draw);
+        }
+
+        for bird in model.replay.birds(){
+            bird.draw_sep_region(&draw);
+        }
+    }
+
+    if model.settings.show_trails{
+        for bird in model.replay.birds(){
+            bird.draw_trail(&draw);
+        }
+    }
+
+    for bird in model.replay.birds(){
+        bird.draw(&draw);
+    }
+
+    for predator in model.replay.predators(){
+        predator.draw(&draw);
+    }
+
+    crate::draw_letterbox(&screen, &viewport);
+
+    if model.settings.show_debug{
+        draw_meta(app, model, &screen);
+    }
+
+    screen.background().color(BLACK);
+    screen.to_frame(app, &frame).unwrap();
+}
+
+/* Open a window playing back a recording rather than running the simulation */
+pub fn run(recording: Recording)
+{
+    *RECORDING.lock().unwrap() = Some(recording);
+
+    nannou::app(model)
+        .update(update)
+        .view(view)
+        .run();
+}
@@ -0,0 +1,96 @@
+use nannou::prelude::*;
+
+use crate::obstacle::Avoidance;
+
+/* Point the flock is drawn towards or driven away from, e.g. while dragging the mouse */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Pointer{
+    Attract(Point2),
+    Repel(Point2),
+}
+
+impl Pointer{
+    pub fn position(&self) -> Point2{
+        match self{
+            Pointer::Attract(position) => *position,
+            Pointer::Repel(position) => *position,
+        }
+    }
+
+    /* Same pointer somewhere else */
+    pub fn moved_to(&self, position:Point2) -> Pointer{
+        match self{
+            Pointer::Attract(_) => Pointer::Attract(position),
+            Pointer::Repel(_) => Pointer::Repel(position),
+        }
+    }
+
+    /* Heading a bird at the position should turn to and how far it is from the
+     * pointer, None when it's out of range */
+    pub fn lure(&self, position:Point2, range:f32) -> Option<Avoidance>{
+        let offset = match self{
+            Pointer::Attract(pointer) => *pointer - position,
+            Pointer::Repel(pointer) => position - *pointer,
+        };
+
+        let distance = offset.length();
+        if distance < range && distance > 0.0{
+            Some(Avoidance{
+                angle: offset.y.atan2(offset.x),
+                distance,
+            })
+        }
+        else{
+            None
+        }
+    }
+
+    pub fn draw(&self, draw: &Draw, radius:f32)
+    {
+        let (r, g, b) = match self{
+            Pointer::Attract(_) => (60, 200, 90),
+            Pointer::Repel(_) => (220, 60, 40),
+        };
+
+        draw.ellipse()
+            .xy(self.position())
+            .w_h(radius * 2.0, radius * 2.0)
+            .no_fill()
+            .stroke_weight(1.5)
+            .stroke(rgba8(r, g, b, 80));
+
+        draw.ellipse()
+            .xy(self.position())
+            .w_h(8.0, 8.0)
+            .rgb8(r, g, b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const FLOAT_PRECISION:f32 = 0.0001;
+
+    #[test]
+    fn attract_and_repel(){
+        let attract = Pointer::Attract(pt2(100.0, 0.0));
+        let lure = attract.lure(pt2(0.0, 0.0), 200.0).unwrap();
+        assert!((lure.distance - 100.0).abs() < FLOAT_PRECISION);
+        assert!(lure.angle.abs() < FLOAT_PRECISION);
+
+        assert_eq!(attract.moved_to(pt2(0.0, 100.0)), Pointer::Attract(pt2(0.0, 100.0)));
+
+        let repel = Pointer::Repel(pt2(0.0, 100.0));
+        let lure = repel.lure(pt2(0.0, 0.0), 200.0).unwrap();
+        assert!((lure.angle + std::f32::consts::PI / 2.0).abs() < FLOAT_PRECISION);
+    }
+
+    #[test]
+    fn out_of_range(){
+        let pointer = Pointer::Attract(pt2(0.0, 0.0));
+        assert!(pointer.lure(pt2(300.0, 0.0), 200.0).is_none());
+
+        /* Right on the pointer there's no direction to turn in */
+        assert!(pointer.lure(pt2(0.0, 0.0), 200.0).is_none());
+    }
+}
@@ -0,0 +1,193 @@
+use nannou::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::angle;
+use crate::bird::Bird;
+use crate::boundary::Boundary;
+use crate::calcs;
+use crate::obstacle::Avoidance;
+
+/* What a predator chases */
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Pursuit{
+    Nearest,
+    Centroid,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PredatorConfig{
+    pub speed:f32, // per second
+    pub turn_rate:f32, // radians per second
+    pub pursuit:Pursuit,
+}
+
+impl PredatorConfig{
+    const SPEED:f32 = 480.0;
+    const TURN_RATE:f32 = 2.5;
+
+    pub fn new() -> PredatorConfig{
+        PredatorConfig{
+            speed: Self::SPEED,
+            turn_rate: Self::TURN_RATE,
+            pursuit: Pursuit::Nearest,
+        }
+    }
+}
+
+impl Default for PredatorConfig{
+    fn default() -> PredatorConfig{
+        PredatorConfig::new()
+    }
+}
+
+/* Flies at a constant speed and turns towards its quarry as fast as its turn rate allows */
+#[derive(Copy, Clone, Debug)]
+pub struct Predator{
+    xy:Point2,
+    angle:f32,
+    config:PredatorConfig,
+}
+
+impl Predator{
+    const HEIGHT:f32 = 48.0;
+    const WIDTH_2:f32 = 18.0;
+
+    pub fn new(position:Point2, angle:f32, config:PredatorConfig) -> Predator{
+        Predator{
+            xy: position,
+            angle: angle::wrap(angle),
+            config,
+        }
+    }
+
+    pub fn position(&self) -> Point2{
+        self.xy
+    }
+
+    pub fn angle(&self) -> f32{
+        self.angle
+    }
+
+    pub fn config(&self) -> &PredatorConfig{
+        &self.config
+    }
+
+    /* Point being chased, None once there is nothing left to chase */
+    pub fn target(&self, birds:&[Bird]) -> Option<Point2>{
+        if birds.is_empty(){
+            return None;
+        }
+
+        match self.config.pursuit{
+            Pursuit::Nearest => birds.iter()
+                .map(|b| b.position())
+                .min_by(|a, b| self.xy.distance_squared(*a).total_cmp(&self.xy.distance_squared(*b))),
+            Pursuit::Centroid => Some(calcs::average_position(birds)),
+        }
+    }
+
+    /* Advance the predator by dt seconds, predators don't turn back at the edge of the world */
+    pub fn update(&mut self, birds:&[Bird], boundary:Boundary, win:&Rect<f32>, dt:f32){
+        if let Some(target) = self.target(birds){
+            let offset = target - self.xy;
+            let delta = angle::wrap_180(offset.y.atan2(offset.x) - self.angle);
+            let max_turn = self.config.turn_rate * dt;
+            self.angle = angle::wrap(self.angle + delta.clamp(-max_turn, max_turn));
+        }
+
+        self.xy.x += self.config.speed * dt * self.angle.cos();
+        self.xy.y += self.config.speed * dt * self.angle.sin();
+
+        boundary.contain(&mut self.xy, &mut self.angle, win, 0.0);
+    }
+
+    /* Nearest predator within range of a point, as the direction to flee in and how far away it is */
+    pub fn nearest(predators:&[Predator], position:Point2, range:f32) -> Option<Avoidance>{
+        let mut nearest:Option<Avoidance> = None;
+        for predator in predators{
+            let away = position - predator.xy;
+            let distance = away.length();
+            if distance < range && nearest.is_none_or(|n| distance < n.distance){
+                nearest = Some(Avoidance{
+                    angle: away.y.atan2(away.x),
+                    distance,
+                });
+            }
+        }
+        nearest
+    }
+
+    /* Larger than a bird and notched at the back so it stands out in the flock */
+    pub fn draw(&self, draw: &Draw)
+    {
+        let points = [
+            pt2(Self::HEIGHT / 2.0, 0.0),
+            pt2(-Self::HEIGHT / 2.0, Self::WIDTH_2),
+            pt2(-Self::HEIGHT / 4.0, 0.0),
+            pt2(-Self::HEIGHT / 2.0, -Self::WIDTH_2),
+        ];
+
+        draw.polygon()
+            .points(points)
+            .x_y(self.xy.x, self.xy.y)
+            .rotate(self.angle)
+            .rgb8(220, 60, 40);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bird::BirdConfig;
+    const FLOAT_PRECISION:f32 = 0.0001;
+
+    fn win() -> Rect<f32>{
+        Rect::from_w_h(1000.0, 1000.0)
+    }
+
+    #[test]
+    fn chase_nearest(){
+        let config = BirdConfig::new();
+        let birds = vec![Bird::new(pt2(0.0, 300.0), 0.0, config), Bird::new(pt2(100.0, 0.0), 0.0, config)];
+        let mut predator = Predator::new(pt2(0.0, 0.0), deg_to_rad(180.0), PredatorConfig::new());
+
+        assert_eq!(predator.target(&birds), Some(pt2(100.0, 0.0)));
+
+        /* Turns no faster than its turn rate */
+        predator.update(&birds, Boundary::TurnBox, &win(), 0.1);
+        assert!((angle::wrap_180(predator.angle() - deg_to_rad(180.0)).abs() - 0.25).abs() < FLOAT_PRECISION);
+    }
+
+    #[test]
+    fn chase_centroid(){
+        let config = BirdConfig::new();
+        let birds = vec![Bird::new(pt2(-100.0, 50.0), 0.0, config), Bird::new(pt2(300.0, 50.0), 0.0, config)];
+        let mut predator_config = PredatorConfig::new();
+        predator_config.pursuit = Pursuit::Centroid;
+        let predator = Predator::new(pt2(0.0, 0.0), 0.0, predator_config);
+
+        assert_eq!(predator.target(&birds), Some(pt2(100.0, 50.0)));
+        assert_eq!(predator.target(&[]), None);
+    }
+
+    #[test]
+    fn wraps_round(){
+        let mut predator = Predator::new(pt2(495.0, 0.0), 0.0, PredatorConfig::new());
+        predator.update(&[], Boundary::TurnBox, &win(), 0.1);
+        assert!((predator.position().x - (-457.0)).abs() < FLOAT_PRECISION);
+        assert_eq!(predator.angle(), 0.0);
+    }
+
+    #[test]
+    fn nearest_threat(){
+        let config = PredatorConfig::new();
+        let predators = vec![Predator::new(pt2(0.0, 0.0), 0.0, config), Predator::new(pt2(0.0, 100.0), 0.0, config)];
+
+        let threat = Predator::nearest(&predators, pt2(0.0, 70.0), 50.0).unwrap();
+        assert!((threat.distance - 30.0).abs() < FLOAT_PRECISION);
+        assert!((threat.angle + std::f32::consts::PI / 2.0).abs() < FLOAT_PRECISION);
+
+        assert!(Predator::nearest(&predators, pt2(200.0, 0.0), 50.0).is_none());
+    }
+}
@@ -37,6 +37,7 @@ pub struct Proximity{
     settings:ProximitySettings,
     angle:f32, // measured angle
     alignment:f32, // average alignment
+    strength:f32, // scales the turn, from the weighting
     changed:bool,
 }
 
@@ -46,6 +47,7 @@ impl Proximity{
             settings:settings,
             angle:angle,
             alignment:alignment,
+            strength:1.0,
             changed:false,
         }
     }
@@ -59,9 +61,10 @@ impl Proximity{
         self.settings
     }
 
-    pub fn update(&mut self, angle:f32, alignment:f32){
+    pub fn update(&mut self, angle:f32, alignment:f32, strength:f32){
         self.angle = angle;
         self.alignment = alignment;
+        self.strength = strength;
         self.changed = true;
     }
 
@@ -77,6 +80,10 @@ impl Proximity{
         self.alignment
     }
 
+    pub fn strength(&self) -> f32{
+        self.strength
+    }
+
     pub fn changed(&self) -> bool{
         self.changed
     }
@@ -0,0 +1,668 @@
+use nannou::prelude::*;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::bird::{Bird, BirdConfig, TRAIL_LEN};
+use crate::containment::Containment;
+use crate::obstacle::Obstacle;
+use crate::predator::{Predator, PredatorConfig};
+use crate::world::World;
+
+/* Compact binary trajectory file, all values little endian:
+ *
+ *   header: magic (8 bytes), version (u32), seed (u64), tick rate (f32), bounds width (f32), bounds height (f32),
+ *           number of obstacles (u32) then each obstacle, containment
+ *   frame:  number of birds (u32), then x (f32), y (f32), angle (f32) for each bird,
+ *           then the same again for the predators
+ *
+ * Shapes are a tag (u32) followed by their points and sizes as f32s, polygons giving
+ * their number of points (u32) first. A containment tag of 0 means there isn't one.
+ *
+ * Frames carry their own counts so the flock can grow or shrink during a recording */
+const MAGIC:&[u8; 8] = b"LLOIDREC";
+const VERSION:u32 = 3;
+
+/* Counts are checked against these before anything is allocated, so a corrupt
+ * file is reported rather than running out of memory */
+const MAX_POSES:u32 = 1 << 20;
+const MAX_SHAPES:u32 = 1 << 16;
+const MAX_POINTS:u32 = 1 << 16;
+
+const CIRCLE:u32 = 1;
+const RECT:u32 = 2;
+const ELLIPSE:u32 = 3;
+const POLYGON:u32 = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pose{
+    pub xy:Point2,
+    pub angle:f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame{
+    pub birds:Vec<Pose>,
+    pub predators:Vec<Pose>,
+}
+
+pub struct Recorder<W: Write>{
+    writer:W,
+}
+
+fn write_f32<W: Write>(writer:&mut W, value:f32) -> io::Result<()>{
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_points<W: Write>(writer:&mut W, points:&[Point2]) -> io::Result<()>{
+    writer.write_all(&(points.len() as u32).to_le_bytes())?;
+    for point in points{
+        write_f32(writer, point.x)?;
+        write_f32(writer, point.y)?;
+    }
+    Ok(())
+}
+
+fn write_obstacle<W: Write>(writer:&mut W, obstacle:&Obstacle) -> io::Result<()>{
+    match obstacle{
+        Obstacle::Circle{centre, radius} => {
+            writer.write_all(&CIRCLE.to_le_bytes())?;
+            write_f32(writer, centre.x)?;
+            write_f32(writer, centre.y)?;
+            write_f32(writer, *radius)
+        },
+        Obstacle::Rect{rect} => {
+            writer.write_all(&RECT.to_le_bytes())?;
+            write_f32(writer, rect.x())?;
+            write_f32(writer, rect.y())?;
+            write_f32(writer, rect.w())?;
+            write_f32(writer, rect.h())
+        },
+        Obstacle::Polygon{points} => {
+            writer.write_all(&POLYGON.to_le_bytes())?;
+            write_points(writer, points)
+        },
+    }
+}
+
+fn write_containment<W: Write>(writer:&mut W, containment:Option<&Containment>) -> io::Result<()>{
+    match containment{
+        None => writer.write_all(&0u32.to_le_bytes()),
+        Some(Containment::Rect{rect}) => {
+            writer.write_all(&RECT.to_le_bytes())?;
+            write_f32(writer, rect.x())?;
+            write_f32(writer, rect.y())?;
+            write_f32(writer, rect.w())?;
+            write_f32(writer, rect.h())
+        },
+        Some(Containment::Circle{centre, radius}) => {
+            writer.write_all(&CIRCLE.to_le_bytes())?;
+            write_f32(writer, centre.x)?;
+            write_f32(writer, centre.y)?;
+            write_f32(writer, *radius)
+        },
+        Some(Containment::Ellipse{centre, radii}) => {
+            writer.write_all(&ELLIPSE.to_le_bytes())?;
+            write_f32(writer, centre.x)?;
+            write_f32(writer, centre.y)?;
+            write_f32(writer, radii.x)?;
+            write_f32(writer, radii.y)
+        },
+        Some(Containment::Polygon{points}) => {
+            writer.write_all(&POLYGON.to_le_bytes())?;
+            write_points(writer, points)
+        },
+    }
+}
+
+impl Recorder<BufWriter<File>>{
+    pub fn create(path:&Path, world:&World) -> io::Result<Recorder<BufWriter<File>>>{
+        Recorder::new(BufWriter::new(File::create(path)?), world)
+    }
+}
+
+impl<W: Write> Recorder<W>{
+    /* Writes the header and the world as it is now as the first frame */
+    pub fn new(mut writer:W, world:&World) -> io::Result<Recorder<W>>{
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&world.seed().to_le_bytes())?;
+        writer.write_all(&world.tick_rate().to_le_bytes())?;
+        writer.write_all(&world.bounds().w().to_le_bytes())?;
+        writer.write_all(&world.bounds().h().to_le_bytes())?;
+        writer.write_all(&(world.obstacles().len() as u32).to_le_bytes())?;
+        for obstacle in world.obstacles(){
+            write_obstacle(&mut writer, obstacle)?;
+        }
+        write_containment(&mut writer, world.containment())?;
+
+        let mut recorder = Recorder{
+            writer,
+        };
+        recorder.record(world)?;
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, world:&World) -> io::Result<()>{
+        self.writer.write_all(&(world.birds().len() as u32).to_le_bytes())?;
+        for bird in world.birds(){
+            self.writer.write_all(&bird.position().x.to_le_bytes())?;
+            self.writer.write_all(&bird.position().y.to_le_bytes())?;
+            self.writer.write_all(&bird.angle().to_le_bytes())?;
+        }
+
+        self.writer.write_all(&(world.predators().len() as u32).to_le_bytes())?;
+        for predator in world.predators(){
+            self.writer.write_all(&predator.position().x.to_le_bytes())?;
+            self.writer.write_all(&predator.position().y.to_le_bytes())?;
+            self.writer.write_all(&predator.angle().to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()>{
+        self.writer.flush()
+    }
+}
+
+pub struct Recording{
+    seed:u64,
+    tick_rate:f32,
+    bounds:Rect<f32>,
+    obstacles:Vec<Obstacle>,
+    containment:Option<Containment>,
+    frames:Vec<Frame>,
+}
+
+fn read_u32<R: Read>(reader:&mut R) -> io::Result<u32>{
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader:&mut R) -> io::Result<u64>{
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(reader:&mut R) -> io::Result<f32>{
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+fn invalid(reason:&str) -> io::Error{
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+fn read_count<R: Read>(reader:&mut R, max:u32, reason:&str) -> io::Result<u32>{
+    let count = read_u32(reader)?;
+    if count > max{
+        return Err(invalid(reason));
+    }
+    Ok(count)
+}
+
+fn read_point<R: Read>(reader:&mut R) -> io::Result<Point2>{
+    let x = read_f32(reader)?;
+    let y = read_f32(reader)?;
+    if !x.is_finite() || !y.is_finite(){
+        return Err(invalid("recording has a point that isn't finite"));
+    }
+    Ok(pt2(x, y))
+}
+
+/* Sizes are checked here as the shape constructors assert on them */
+fn read_size<R: Read>(reader:&mut R) -> io::Result<f32>{
+    let size = read_f32(reader)?;
+    if !size.is_finite() || size <= 0.0{
+        return Err(invalid("recording has a shape with no size"));
+    }
+    Ok(size)
+}
+
+fn read_points<R: Read>(reader:&mut R) -> io::Result<Vec<Point2>>{
+    let num_points = read_count(reader, MAX_POINTS, "recording has too many points in a polygon")?;
+    let mut points = Vec::with_capacity(num_points as usize);
+    for _i in 0..num_points{
+        points.push(read_point(reader)?);
+    }
+    Ok(points)
+}
+
+fn read_rect<R: Read>(reader:&mut R) -> io::Result<Rect<f32>>{
+    let centre = read_point(reader)?;
+    let w = read_size(reader)?;
+    let h = read_size(reader)?;
+    Ok(Rect::from_x_y_w_h(centre.x, centre.y, w, h))
+}
+
+fn read_obstacle<R: Read>(reader:&mut R) -> io::Result<Obstacle>{
+    match read_u32(reader)?{
+        CIRCLE => {
+            let centre = read_point(reader)?;
+            Ok(Obstacle::circle(centre, read_size(reader)?))
+        },
+        RECT => Ok(Obstacle::rect(read_rect(reader)?)),
+        POLYGON => {
+            let points = read_points(reader)?;
+            if !Obstacle::is_convex(&points){
+                return Err(invalid("recording has an obstacle that isn't convex"));
+            }
+            Ok(Obstacle::polygon(points))
+        },
+        _ => Err(invalid("recording has an unknown obstacle")),
+    }
+}
+
+fn read_containment<R: Read>(reader:&mut R) -> io::Result<Option<Containment>>{
+    match read_u32(reader)?{
+        0 => Ok(None),
+        RECT => Ok(Some(Containment::rect(read_rect(reader)?))),
+        CIRCLE => {
+            let centre = read_point(reader)?;
+            Ok(Some(Containment::circle(centre, read_size(reader)?)))
+        },
+        ELLIPSE => {
+            let centre = read_point(reader)?;
+            let radii = vec2(read_size(reader)?, read_size(reader)?);
+            Ok(Some(Containment::ellipse(centre, radii)))
+        },
+        POLYGON => {
+            let points = read_points(reader)?;
+            if !Containment::is_polygon(&points){
+                return Err(invalid("recording has a containment polygon with no area"));
+            }
+            Ok(Some(Containment::polygon(points)))
+        },
+        _ => Err(invalid("recording has an unknown containment")),
+    }
+}
+
+fn read_poses<R: Read>(reader:&mut R, num_poses:u32) -> io::Result<Vec<Pose>>{
+    let mut poses = Vec::with_capacity(num_poses as usize);
+    for _i in 0..num_poses{
+        let x = read_f32(reader)?;
+        let y = read_f32(reader)?;
+        let angle = read_f32(reader)?;
+        poses.push(Pose{xy: pt2(x, y), angle});
+    }
+    Ok(poses)
+}
+
+impl Recording{
+    pub fn load(path:&Path) -> io::Result<Recording>{
+        Recording::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read<R: Read>(mut reader:R) -> io::Result<Recording>{
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC{
+            return Err(invalid("not a lloids recording"));
+        }
+
+        if read_u32(&mut reader)? != VERSION{
+            return Err(invalid("unsupported recording version"));
+        }
+
+        let seed = read_u64(&mut reader)?;
+        let tick_rate = read_f32(&mut reader)?;
+        if !tick_rate.is_finite() || tick_rate <= 0.0{
+            return Err(invalid("recording has no tick rate"));
+        }
+        let w = read_f32(&mut reader)?;
+        let h = read_f32(&mut reader)?;
+
+        let num_obstacles = read_count(&mut reader, MAX_SHAPES, "recording has too many obstacles")?;
+        let mut obstacles = Vec::with_capacity(num_obstacles as usize);
+        for _i in 0..num_obstacles{
+            obstacles.push(read_obstacle(&mut reader)?);
+        }
+        let containment = read_containment(&mut reader)?;
+
+        let mut frames = Vec::new();
+        loop{
+            /* A clean end of file can only happen between frames */
+            let num_birds = match read_u32(&mut reader){
+                Ok(num_birds) => num_birds,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            };
+            if num_birds > MAX_POSES{
+                return Err(invalid("recording has too many birds in a frame"));
+            }
+            let birds = read_poses(&mut reader, num_birds)?;
+
+            let num_predators = read_count(&mut reader, MAX_POSES, "recording has too many predators in a frame")?;
+            let predators = read_poses(&mut reader, num_predators)?;
+
+            frames.push(Frame{
+                birds,
+                predators,
+            });
+        }
+
+        if frames.is_empty(){
+            return Err(invalid("recording has no frames"));
+        }
+
+        Ok(Recording{
+            seed,
+            tick_rate,
+            bounds: Rect::from_w_h(w, h),
+            obstacles,
+            containment,
+            frames,
+        })
+    }
+
+    pub fn seed(&self) -> u64{
+        self.seed
+    }
+
+    /* Frames per second of simulated time */
+    pub fn tick_rate(&self) -> f32{
+        self.tick_rate
+    }
+
+    pub fn bounds(&self) -> Rect<f32>{
+        self.bounds
+    }
+
+    pub fn obstacles(&self) -> &Vec<Obstacle>{
+        &self.obstacles
+    }
+
+    pub fn containment(&self) -> Option<&Containment>{
+        self.containment.as_ref()
+    }
+
+    pub fn len(&self) -> usize{
+        self.frames.len()
+    }
+
+    /* Never true for a recording that has been read, there is always a first frame */
+    pub fn is_empty(&self) -> bool{
+        self.frames.is_empty()
+    }
+
+    pub fn frame(&self, index:usize) -> &Frame{
+        &self.frames[index]
+    }
+}
+
+/* Plays a recording back as a flock of birds, so the usual drawing and overlays
+ * can be used. The play head is fractional so playback can run slower than one
+ * recorded frame per update */
+pub struct Replay{
+    recording:Recording,
+    config:BirdConfig,
+    birds:Vec<Bird>,
+    predators:Vec<Predator>,
+    frame:usize,
+    head:f32,
+    speed:f32,
+}
+
+impl Replay{
+    pub const SPEED_MIN:f32 = 0.125;
+    pub const SPEED_MAX:f32 = 8.0;
+
+    /* The config only sets what can't be recorded, such as the radii drawn around each bird */
+    pub fn new(recording:Recording, config:BirdConfig) -> Replay{
+        let mut replay = Replay{
+            recording,
+            config,
+            birds: Vec::new(),
+            predators: Vec::new(),
+            frame: 0,
+            head: 0.0,
+            speed: 1.0,
+        };
+        replay.place_frame(0);
+        replay
+    }
+
+    pub fn recording(&self) -> &Recording{
+        &self.recording
+    }
+
+    pub fn birds(&self) -> &[Bird]{
+        &self.birds
+    }
+
+    pub fn predators(&self) -> &[Predator]{
+        &self.predators
+    }
+
+    pub fn frame(&self) -> usize{
+        self.frame
+    }
+
+    pub fn speed(&self) -> f32{
+        self.speed
+    }
+
+    pub fn finished(&self) -> bool{
+        self.frame == self.recording.len() - 1
+    }
+
+    pub fn faster(&mut self){
+        self.speed = (self.speed * 2.0).min(Self::SPEED_MAX);
+    }
+
+    pub fn slower(&mut self){
+        self.speed = (self.speed * 0.5).max(Self::SPEED_MIN);
+    }
+
+    /* Move the play head on by a number of frames at the current speed */
+    pub fn advance(&mut self, frames:f32){
+        let last = (self.recording.len() - 1) as f32;
+        self.head = (self.head + (frames * self.speed)).min(last);
+        self.show(self.head as usize);
+    }
+
+    pub fn seek(&mut self, frame:usize){
+        let frame = frame.min(self.recording.len() - 1);
+        self.head = frame as f32;
+        self.show(frame);
+    }
+
+    pub fn scrub(&mut self, frames:i64){
+        let frame = (self.frame as i64 + frames).max(0);
+        self.seek(frame as usize);
+    }
+
+    /* Playing forwards only needs the new frames, anything else is rebuilt from far
+     * enough back to fill the trails */
+    fn show(&mut self, frame:usize){
+        if frame == self.frame{
+            return;
+        }
+
+        let first:usize;
+        if frame > self.frame && frame - self.frame <= TRAIL_LEN{
+            first = self.frame + 1;
+        }
+        else{
+            first = frame.saturating_sub(TRAIL_LEN);
+            self.birds.clear();
+        }
+
+        for index in first..=frame{
+            self.place_frame(index);
+        }
+        self.frame = frame;
+    }
+
+    fn place_frame(&mut self, index:usize){
+        let frame = &self.recording.frames[index];
+
+        self.birds.truncate(frame.birds.len());
+        for pose in &frame.birds[self.birds.len()..]{
+            self.birds.push(Bird::new(pose.xy, pose.angle, self.config));
+        }
+
+        for (bird, pose) in self.birds.iter_mut().zip(&frame.birds){
+            bird.place(pose.xy, pose.angle);
+        }
+
+        /* Predators have no trail, so only the frame being shown matters */
+        self.predators = frame.predators.iter()
+            .map(|pose| Predator::new(pose.xy, pose.angle, PredatorConfig::new()))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poses(world:&World) -> Frame{
+        Frame{
+            birds: world.birds().iter().map(|b| Pose{xy: b.position(), angle: b.angle()}).collect(),
+            predators: world.predators().iter().map(|p| Pose{xy: p.position(), angle: p.angle()}).collect(),
+        }
+    }
+
+    fn record_world(steps:usize) -> Vec<u8>{
+        let mut world = World::new(Rect::from_w_h(1920.0, 1080.0), BirdConfig::new(), 5);
+        let area = world.inner();
+        world.spawn(10, &area);
+
+        let mut bytes:Vec<u8> = Vec::new();
+        {
+            let mut recorder = Recorder::new(&mut bytes, &world).unwrap();
+            for _i in 0..steps{
+                world.step();
+                recorder.record(&world).unwrap();
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn round_trip(){
+        let mut world = World::new(Rect::from_w_h(1920.0, 1080.0), BirdConfig::new(), 77);
+        let area = world.inner();
+        world.spawn(12, &area);
+        world.spawn_predators(2, PredatorConfig::new(), &area);
+        world.set_obstacles(vec![
+            Obstacle::circle(pt2(100.0, 0.0), 50.0),
+            Obstacle::rect(Rect::from_x_y_w_h(-300.0, 200.0, 80.0, 40.0)),
+            Obstacle::polygon(vec![pt2(0.0, -300.0), pt2(60.0, -250.0), pt2(-60.0, -250.0)]),
+        ]);
+        world.set_containment(Some(Containment::ellipse(pt2(0.0, 0.0), vec2(900.0, 500.0))));
+
+        let mut bytes:Vec<u8> = Vec::new();
+        let mut expected:Vec<Frame> = Vec::new();
+        {
+            let mut recorder = Recorder::new(&mut bytes, &world).unwrap();
+            expected.push(poses(&world));
+            for _i in 0..20{
+                world.step();
+                recorder.record(&world).unwrap();
+                expected.push(poses(&world));
+            }
+        }
+
+        let recording = Recording::read(&bytes[..]).unwrap();
+        assert_eq!(recording.seed(), 77);
+        assert_eq!(recording.tick_rate(), World::TICK_RATE);
+        assert_eq!(recording.bounds().w(), 1920.0);
+        assert_eq!(recording.bounds().h(), 1080.0);
+        assert_eq!(recording.obstacles(), world.obstacles());
+        assert_eq!(recording.containment(), world.containment());
+        assert_eq!(recording.len(), 21);
+        for (i, frame) in expected.iter().enumerate(){
+            assert_eq!(recording.frame(i), frame);
+        }
+        assert_eq!(recording.frame(20).predators.len(), 2);
+
+        let mut replay = Replay::new(recording, BirdConfig::new());
+        replay.seek(20);
+        assert_eq!(replay.predators()[1].position(), world.predators()[1].position());
+    }
+
+    #[test]
+    fn not_a_recording(){
+        let bytes = b"NOTLLOIDS_______________________";
+        assert!(Recording::read(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn truncated_frame(){
+        let world = World::new(Rect::from_w_h(100.0, 100.0), BirdConfig::new(), 1);
+        let mut bytes:Vec<u8> = Vec::new();
+        Recorder::new(&mut bytes, &world).unwrap();
+
+        /* A frame that claims a bird but has no data for it */
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&1.0f32.to_le_bytes());
+        assert!(Recording::read(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn corrupt_counts(){
+        let world = World::new(Rect::from_w_h(100.0, 100.0), BirdConfig::new(), 1);
+        let mut bytes:Vec<u8> = Vec::new();
+        Recorder::new(&mut bytes, &world).unwrap();
+
+        /* Claims far more birds than could ever have been recorded, which is an error rather than an abort */
+        let mut corrupt = bytes.clone();
+        corrupt.extend_from_slice(&u32::MAX.to_le_bytes());
+        let err = Recording::read(&corrupt[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        /* As does a huge polygon in the header, after the magic, version, seed, tick rate and bounds */
+        let mut corrupt = bytes[..32].to_vec();
+        corrupt.extend_from_slice(&1u32.to_le_bytes());
+        corrupt.extend_from_slice(&POLYGON.to_le_bytes());
+        corrupt.extend_from_slice(&u32::MAX.to_le_bytes());
+        let err = Recording::read(&corrupt[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn scrubbing_matches_playback(){
+        let bytes = record_world(200);
+        let mut played = Replay::new(Recording::read(&bytes[..]).unwrap(), BirdConfig::new());
+        let mut scrubbed = Replay::new(Recording::read(&bytes[..]).unwrap(), BirdConfig::new());
+
+        for _i in 0..150{
+            played.advance(1.0);
+        }
+        scrubbed.seek(199);
+        scrubbed.scrub(-49);
+
+        assert_eq!(played.frame(), 150);
+        assert_eq!(scrubbed.frame(), 150);
+        for (a, b) in played.birds().iter().zip(scrubbed.birds()){
+            assert_eq!(a.position(), b.position());
+            assert_eq!(a.angle(), b.angle());
+        }
+    }
+
+    #[test]
+    fn playback_speed(){
+        let bytes = record_world(100);
+        let mut replay = Replay::new(Recording::read(&bytes[..]).unwrap(), BirdConfig::new());
+
+        replay.slower();
+        replay.advance(1.0);
+        assert_eq!(replay.frame(), 0);
+        replay.advance(1.0);
+        assert_eq!(replay.frame(), 1);
+
+        replay.faster();
+        replay.faster();
+        replay.advance(10.0);
+        assert_eq!(replay.frame(), 21);
+
+        replay.advance(1000.0);
+        assert!(replay.finished());
+        assert_eq!(replay.frame(), 100);
+    }
+}
@@ -0,0 +1,147 @@
+use nannou::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::bird::Bird;
+use crate::calcs;
+
+/* How the flocking rules turn a bird. Angular is the original model, which rotates the
+ * heading by a fixed delta per rule. Vector is Reynolds' formulation, where each rule asks
+ * for a velocity and the bird is pushed towards the weighted sum, limited by a maximum force */
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SteeringModel{
+    #[default]
+    Angular,
+    Vector,
+}
+
+impl SteeringModel{
+    pub fn name(&self) -> &'static str{
+        match self{
+            SteeringModel::Angular => "Angular",
+            SteeringModel::Vector => "Vector",
+        }
+    }
+
+    pub fn toggle(&self) -> SteeringModel{
+        match self{
+            SteeringModel::Angular => SteeringModel::Vector,
+            SteeringModel::Vector => SteeringModel::Angular,
+        }
+    }
+}
+
+/* Steering force towards flying flat out in a direction, no more than the bird's maximum force */
+pub fn steer(bird:&Bird, direction:Vec2) -> Vec2{
+    if direction == Vec2::ZERO{
+        return Vec2::ZERO;
+    }
+
+    let desired = direction.normalize() * bird.max_speed();
+    (desired - bird.velocity()).clamp_length_max(bird.max_force())
+}
+
+/* Away from each neighbour, how much harder nearer ones push depends on the weighting */
+pub fn separation<'a, I>(bird:&Bird, other_birds:I) -> Vec2
+    where I: IntoIterator<Item = &'a Bird> + Clone
+{
+    let weighting = bird.tuning().separation_weighting;
+    let radius = bird.separation_radius();
+    let mut away = Vec2::ZERO;
+    for other in other_birds.clone(){
+        let offset = bird.position() - other.position();
+        let distance = offset.length();
+        if distance > 0.0{
+            away += (offset / distance) * weighting.weight(distance, radius);
+        }
+    }
+    steer(bird, away) * calcs::strength(bird.position(), other_birds, weighting, radius)
+}
+
+/* Towards the neighbours' average velocity */
+pub fn alignment<'a, I>(bird:&Bird, other_birds:I) -> Vec2
+    where I: IntoIterator<Item = &'a Bird>
+{
+    let heading = other_birds.into_iter().fold(Vec2::ZERO, |sum, other| sum + other.velocity());
+    steer(bird, heading)
+}
+
+/* Towards the neighbours' centre, weighted by distance */
+pub fn cohesion<'a, I>(bird:&Bird, other_birds:I) -> Vec2
+    where I: IntoIterator<Item = &'a Bird> + Clone
+{
+    if other_birds.clone().into_iter().next().is_none(){
+        return Vec2::ZERO;
+    }
+
+    let weighting = bird.tuning().cohesion_weighting;
+    let centre = calcs::weighted_position(bird.position(), other_birds.clone(), weighting, bird.radius());
+    steer(bird, centre - bird.position()) * calcs::strength(bird.position(), other_birds, weighting, bird.radius())
+}
+
+/* Weighted sum of the three rules */
+pub fn force<'a, I, J>(bird:&Bird, separation_birds:I, flock_birds:J) -> Vec2
+    where I: IntoIterator<Item = &'a Bird> + Clone,
+          J: IntoIterator<Item = &'a Bird> + Clone
+{
+    let tuning = bird.tuning();
+    (separation(bird, separation_birds) * tuning.separation_weight)
+        + (alignment(bird, flock_birds.clone()) * tuning.alignment_weight)
+        + (cohesion(bird, flock_birds) * tuning.cohesion_weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bird::BirdConfig;
+    use crate::weighting::Weighting;
+    const FLOAT_PRECISION:f32 = 0.001;
+
+    #[test]
+    fn steer_limited_to_max_force(){
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, BirdConfig::new());
+
+        /* Already flying that way, only needs speeding up */
+        let force = steer(&bird, vec2(1.0, 0.0));
+        assert!(force.x > 0.0);
+        assert!(force.y.abs() < FLOAT_PRECISION);
+
+        let force = steer(&bird, vec2(-1.0, 0.0));
+        assert!(force.x < 0.0);
+        assert!((force.length() - bird.max_force()).abs() < FLOAT_PRECISION);
+
+        assert_eq!(steer(&bird, Vec2::ZERO), Vec2::ZERO);
+    }
+
+    #[test]
+    fn rules_point_the_right_way(){
+        let config = BirdConfig::new();
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        let others = [Bird::new(pt2(0.0, 20.0), deg_to_rad(90.0), config), Bird::new(pt2(0.0, 40.0), deg_to_rad(90.0), config)];
+
+        assert!(separation(&bird, &others).y < 0.0);
+        assert!(cohesion(&bird, &others).y > 0.0);
+        assert!(alignment(&bird, &others).y > 0.0);
+    }
+
+    #[test]
+    fn nearer_pushes_harder(){
+        let mut config = BirdConfig::new();
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        let near = [Bird::new(pt2(0.0, 5.0), 0.0, config)];
+        let far = [Bird::new(pt2(0.0, 25.0), 0.0, config)];
+
+        /* Uniform only cares which way */
+        assert!((separation(&bird, &near).length() - separation(&bird, &far).length()).abs() < FLOAT_PRECISION);
+
+        config.tuning.separation_weighting = Weighting::Inverse;
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, config);
+        assert!(separation(&bird, &near).length() > separation(&bird, &far).length() * 2.0);
+    }
+
+    #[test]
+    fn no_neighbours_no_force(){
+        let bird = Bird::new(pt2(0.0, 0.0), 0.0, BirdConfig::new());
+        assert_eq!(force(&bird, &[], &[]), Vec2::ZERO);
+    }
+}
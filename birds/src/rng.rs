@@ -0,0 +1,74 @@
+/* Small deterministic random number generator (SplitMix64) so that a given
+ * seed always produces the same sequence, independent of platform or crate versions */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rng{
+    state:u64,
+}
+
+impl Rng{
+    const GOLDEN_GAMMA:u64 = 0x9E37_79B9_7F4A_7C15;
+
+    pub fn new(seed:u64) -> Rng{
+        Rng{
+            state:seed,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64{
+        self.state = self.state.wrapping_add(Self::GOLDEN_GAMMA);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /* Uniform in [0, 1) using the top 24 bits */
+    pub fn next_f32(&mut self) -> f32{
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /* Uniform in [lower, upper) */
+    pub fn range(&mut self, lower:f32, upper:f32) -> f32{
+        lower + ((upper - lower) * self.next_f32())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence(){
+        let mut a = Rng::new(1234);
+        let mut b = Rng::new(1234);
+
+        for _i in 0..1000{
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn different_seed_different_sequence(){
+        let mut a = Rng::new(1);
+        let mut b = Rng::new(2);
+
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn range_within_limits(){
+        let mut rng = Rng::new(42);
+
+        for _i in 0..10000{
+            let value = rng.range(-5.0, 10.0);
+            assert!(value >= -5.0);
+            assert!(value < 10.0);
+        }
+    }
+
+    #[test]
+    fn range_empty(){
+        let mut rng = Rng::new(42);
+        assert_eq!(rng.range(3.0, 3.0), 3.0);
+    }
+}
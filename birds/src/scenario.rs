@@ -0,0 +1,823 @@
+use nannou::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::bird::BirdConfig;
+use crate::gain::Gain;
+use crate::obstacle::Obstacle;
+use crate::predator::{PredatorConfig, Pursuit};
+use crate::neighbours::Neighbours;
+use crate::reynolds::SteeringModel;
+use crate::weighting::Weighting;
+use crate::boundary::Boundary;
+use crate::containment::Containment;
+use crate::proximity::ProximitySettings;
+use crate::speed::Speed;
+use crate::tuning::Tuning;
+use crate::world::World;
+use crate::settings::Settings;
+
+/* Everything needed to set up a run, loaded from a TOML or JSON file.
+ * Any field left out of the file takes its default value */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed:Option<u64>,
+    pub num_birds:u32,
+    pub turn_offset:f32,
+    pub turn_offset_hard:f32,
+    pub alignment_gain:f32,
+    pub tick_rate:f32,
+    pub steering:SteeringModel,
+    pub boundary:Boundary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds:Option<BoundsSection>,
+    pub speed:SpeedSection,
+    pub separation:ProximitySection,
+    pub cohesion:ProximitySection,
+    pub neighbours:Neighbours,
+    pub bird:TuningSection,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub obstacles:Vec<ObstacleSection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub containment:Option<ContainmentSection>,
+    pub predators:PredatorSection,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings:Option<Settings>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BoundsSection{
+    pub width:f32,
+    pub height:f32,
+}
+
+impl BoundsSection{
+    /* World size when the file doesn't give one, in world units whatever the window size is */
+    pub const DEFAULT:BoundsSection = BoundsSection{width: 1920.0, height: 1080.0};
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SpeedSection{
+    pub min:f32,
+    pub max:f32,
+    pub randomise:bool,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProximitySection{
+    pub speed_min:f32,
+    pub speed_max:f32,
+    pub randomise:bool,
+    pub delta:f32,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TuningSection{
+    pub region_radius:f32,
+    pub separation_radius:f32,
+    pub edge_bleed:f32,
+    pub turn_gain:f32,
+    pub hard_angle_multiplier:f32,
+    pub hard_angle_saturation:f32,
+    pub distance_decay:f32,
+    pub field_of_view:f32,
+    pub separation_field_of_view:f32,
+    pub obstacle_radius:f32,
+    pub obstacle_gain:f32,
+    pub fear_radius:f32,
+    pub fear_gain:f32,
+    pub pointer_radius:f32,
+    pub pointer_gain:f32,
+    pub separation_weight:f32,
+    pub alignment_weight:f32,
+    pub cohesion_weight:f32,
+    pub max_force:f32,
+    pub separation_weighting:Weighting,
+    pub cohesion_weighting:Weighting,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PredatorSection{
+    pub count:u32,
+    pub speed:f32,
+    pub turn_rate:f32,
+    pub pursuit:Pursuit,
+}
+
+/* Obstacles are centred on (x, y) in world coordinates, polygons must be convex */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "shape", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ObstacleSection{
+    Circle{x:f32, y:f32, radius:f32},
+    Rect{x:f32, y:f32, width:f32, height:f32},
+    Polygon{points:Vec<[f32; 2]>},
+}
+
+/* Shape the flock turns back into instead of the bounds, in world coordinates. Ellipses
+ * are sized like rects, polygons can be concave */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "shape", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ContainmentSection{
+    Rect{x:f32, y:f32, width:f32, height:f32},
+    Circle{x:f32, y:f32, radius:f32},
+    Ellipse{x:f32, y:f32, width:f32, height:f32},
+    Polygon{points:Vec<[f32; 2]>},
+}
+
+#[derive(Debug)]
+pub enum ScenarioError{
+    Io(String, std::io::Error),
+    Parse(String, String),
+    Serialise(String, String),
+    Invalid(&'static str, String),
+}
+
+impl fmt::Display for ScenarioError{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match self{
+            ScenarioError::Io(path, err) => write!(f, "unable to access {}: {}", path, err),
+            ScenarioError::Parse(path, err) => write!(f, "unable to parse {}: {}", path, err),
+            ScenarioError::Serialise(path, err) => write!(f, "unable to write {}: {}", path, err),
+            ScenarioError::Invalid(field, reason) => write!(f, "invalid value for `{}`: {}", field, reason),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError{}
+
+impl Default for Scenario{
+    fn default() -> Scenario{
+        Scenario::new(&BirdConfig::new(), Self::NUM_BIRDS)
+    }
+}
+
+impl Default for TuningSection{
+    fn default() -> TuningSection{
+        TuningSection::from(Tuning::new())
+    }
+}
+
+impl From<Tuning> for TuningSection{
+    fn from(tuning:Tuning) -> TuningSection{
+        TuningSection{
+            region_radius: tuning.region_radius,
+            separation_radius: tuning.separation_radius,
+            edge_bleed: tuning.edge_bleed,
+            turn_gain: tuning.turn_gain,
+            hard_angle_multiplier: tuning.hard_angle_multiplier,
+            hard_angle_saturation: tuning.hard_angle_saturation,
+            distance_decay: tuning.distance_decay,
+            field_of_view: tuning.field_of_view,
+            separation_field_of_view: tuning.separation_field_of_view,
+            obstacle_radius: tuning.obstacle_radius,
+            obstacle_gain: tuning.obstacle_gain,
+            fear_radius: tuning.fear_radius,
+            fear_gain: tuning.fear_gain,
+            pointer_radius: tuning.pointer_radius,
+            pointer_gain: tuning.pointer_gain,
+            separation_weight: tuning.separation_weight,
+            alignment_weight: tuning.alignment_weight,
+            cohesion_weight: tuning.cohesion_weight,
+            max_force: tuning.max_force,
+            separation_weighting: tuning.separation_weighting,
+            cohesion_weighting: tuning.cohesion_weighting,
+        }
+    }
+}
+
+impl Default for PredatorSection{
+    fn default() -> PredatorSection{
+        PredatorSection::new(0, PredatorConfig::new())
+    }
+}
+
+impl PredatorSection{
+    pub fn new(count:u32, config:PredatorConfig) -> PredatorSection{
+        PredatorSection{
+            count,
+            speed: config.speed,
+            turn_rate: config.turn_rate,
+            pursuit: config.pursuit,
+        }
+    }
+
+    pub fn config(&self) -> PredatorConfig{
+        PredatorConfig{
+            speed: self.speed,
+            turn_rate: self.turn_rate,
+            pursuit: self.pursuit,
+        }
+    }
+}
+
+impl From<&Obstacle> for ObstacleSection{
+    fn from(obstacle:&Obstacle) -> ObstacleSection{
+        match obstacle{
+            Obstacle::Circle{centre, radius} => ObstacleSection::Circle{x: centre.x, y: centre.y, radius: *radius},
+            Obstacle::Rect{rect} => ObstacleSection::Rect{x: rect.x(), y: rect.y(), width: rect.w(), height: rect.h()},
+            Obstacle::Polygon{points} => ObstacleSection::Polygon{points: points.iter().map(|p| [p.x, p.y]).collect()},
+        }
+    }
+}
+
+impl ObstacleSection{
+    fn validate(&self) -> bool{
+        match self{
+            ObstacleSection::Circle{x, y, radius} => x.is_finite() && y.is_finite() && radius.is_finite() && *radius > 0.0,
+            ObstacleSection::Rect{x, y, width, height} => x.is_finite() && y.is_finite() && width.is_finite() && height.is_finite() && *width > 0.0 && *height > 0.0,
+            ObstacleSection::Polygon{points} => {
+                let points:Vec<Point2> = points.iter().map(|p| pt2(p[0], p[1])).collect();
+                points.iter().all(|p| p.is_finite()) && Obstacle::is_convex(&points)
+            },
+        }
+    }
+
+    pub fn obstacle(&self) -> Obstacle{
+        match self{
+            ObstacleSection::Circle{x, y, radius} => Obstacle::circle(pt2(*x, *y), *radius),
+            ObstacleSection::Rect{x, y, width, height} => Obstacle::rect(Rect::from_x_y_w_h(*x, *y, *width, *height)),
+            ObstacleSection::Polygon{points} => Obstacle::polygon(points.iter().map(|p| pt2(p[0], p[1])).collect()),
+        }
+    }
+}
+
+impl From<&Containment> for ContainmentSection{
+    fn from(containment:&Containment) -> ContainmentSection{
+        match containment{
+            Containment::Rect{rect} => ContainmentSection::Rect{x: rect.x(), y: rect.y(), width: rect.w(), height: rect.h()},
+            Containment::Circle{centre, radius} => ContainmentSection::Circle{x: centre.x, y: centre.y, radius: *radius},
+            Containment::Ellipse{centre, radii} => ContainmentSection::Ellipse{x: centre.x, y: centre.y, width: radii.x * 2.0, height: radii.y * 2.0},
+            Containment::Polygon{points} => ContainmentSection::Polygon{points: points.iter().map(|p| [p.x, p.y]).collect()},
+        }
+    }
+}
+
+impl ContainmentSection{
+    fn validate(&self) -> bool{
+        match self{
+            ContainmentSection::Rect{x, y, width, height} | ContainmentSection::Ellipse{x, y, width, height} => {
+                x.is_finite() && y.is_finite() && width.is_finite() && height.is_finite() && *width > 0.0 && *height > 0.0
+            },
+            ContainmentSection::Circle{x, y, radius} => x.is_finite() && y.is_finite() && radius.is_finite() && *radius > 0.0,
+            ContainmentSection::Polygon{points} => {
+                let points:Vec<Point2> = points.iter().map(|p| pt2(p[0], p[1])).collect();
+                points.iter().all(|p| p.is_finite()) && Containment::is_polygon(&points)
+            },
+        }
+    }
+
+    pub fn containment(&self) -> Containment{
+        match self{
+            ContainmentSection::Rect{x, y, width, height} => Containment::rect(Rect::from_x_y_w_h(*x, *y, *width, *height)),
+            ContainmentSection::Circle{x, y, radius} => Containment::circle(pt2(*x, *y), *radius),
+            ContainmentSection::Ellipse{x, y, width, height} => Containment::ellipse(pt2(*x, *y), vec2(width / 2.0, height / 2.0)),
+            ContainmentSection::Polygon{points} => Containment::polygon(points.iter().map(|p| pt2(p[0], p[1])).collect()),
+        }
+    }
+}
+
+impl From<Speed> for SpeedSection{
+    fn from(speed:Speed) -> SpeedSection{
+        SpeedSection{
+            min: speed.min(),
+            max: speed.max(),
+            randomise: speed.randomise(),
+        }
+    }
+}
+
+impl From<ProximitySettings> for ProximitySection{
+    fn from(settings:ProximitySettings) -> ProximitySection{
+        ProximitySection{
+            speed_min: settings.speed().min(),
+            speed_max: settings.speed().max(),
+            randomise: settings.speed().randomise(),
+            delta: settings.delta(),
+        }
+    }
+}
+
+fn check(valid:bool, field:&'static str, reason:&str) -> Result<(), ScenarioError>{
+    if valid{
+        Ok(())
+    }
+    else{
+        Err(ScenarioError::Invalid(field, reason.to_string()))
+    }
+}
+
+impl Scenario{
+    pub const NUM_BIRDS:u32 = 150;
+
+    pub fn new(config:&BirdConfig, num_birds:u32) -> Scenario{
+        Scenario{
+            seed: None,
+            num_birds,
+            turn_offset: World::TURN_OFFSET,
+            turn_offset_hard: World::TURN_OFFSET_HARD,
+            alignment_gain: config.alignment_gain.gain(),
+            tick_rate: World::TICK_RATE,
+            steering: SteeringModel::default(),
+            boundary: Boundary::default(),
+            bounds: None,
+            speed: SpeedSection::from(config.speed),
+            separation: ProximitySection::from(config.separation),
+            cohesion: ProximitySection::from(config.cohesion),
+            neighbours: Neighbours::default(),
+            bird: TuningSection::from(config.tuning),
+            obstacles: Vec::new(),
+            containment: None,
+            predators: PredatorSection::default(),
+            settings: None,
+        }
+    }
+
+    /* Snapshot of a running world's tuning, e.g. after adjusting it from the debug menu */
+    pub fn from_world(world:&World) -> Scenario{
+        let mut scenario = Scenario::new(world.config(), world.birds().len() as u32);
+        scenario.seed = Some(world.seed());
+        scenario.bounds = Some(BoundsSection{width: world.bounds().w(), height: world.bounds().h()});
+        scenario.turn_offset = world.turn_offset();
+        scenario.turn_offset_hard = world.turn_offset_hard();
+        scenario.tick_rate = world.tick_rate();
+        scenario.steering = world.steering_model();
+        scenario.boundary = world.boundary();
+        scenario.neighbours = world.neighbour_mode();
+        scenario.obstacles = world.obstacles().iter().map(ObstacleSection::from).collect();
+        /* The world keeps its containment when the boundary changes, but only the turn box uses it */
+        if world.boundary().turns(){
+            scenario.containment = world.containment().map(ContainmentSection::from);
+        }
+        if let Some(predator) = world.predators().first(){
+            scenario.predators = PredatorSection::new(world.predators().len() as u32, *predator.config());
+        }
+        scenario
+    }
+
+    /* Format is picked from the extension, anything other than .json is read as TOML */
+    pub fn load(path:&Path) -> Result<Scenario, ScenarioError>{
+        let name = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|err| ScenarioError::Io(name.clone(), err))?;
+
+        let scenario:Scenario = if Self::is_json(path){
+            serde_json::from_str(&text).map_err(|err| ScenarioError::Parse(name.clone(), err.to_string()))?
+        }
+        else{
+            toml::from_str(&text).map_err(|err| ScenarioError::Parse(name.clone(), err.to_string()))?
+        };
+
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn save(&self, path:&Path) -> Result<(), ScenarioError>{
+        let name = path.display().to_string();
+
+        let text = if Self::is_json(path){
+            serde_json::to_string_pretty(self).map_err(|err| ScenarioError::Serialise(name.clone(), err.to_string()))?
+        }
+        else{
+            toml::to_string(self).map_err(|err| ScenarioError::Serialise(name.clone(), err.to_string()))?
+        };
+
+        fs::write(path, text).map_err(|err| ScenarioError::Io(name, err))
+    }
+
+    fn is_json(path:&Path) -> bool{
+        path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    }
+
+    pub fn validate(&self) -> Result<(), ScenarioError>{
+        if let Some(bounds) = self.bounds{
+            check(bounds.width.is_finite() && bounds.width > 0.0, "bounds.width", "must be greater than zero")?;
+            check(bounds.height.is_finite() && bounds.height > 0.0, "bounds.height", "must be greater than zero")?;
+        }
+        let bounds = self.world_bounds();
+        check(self.turn_offset * 2.0 < bounds.w().min(bounds.h()), "turn_offset", "must be less than half the width and height of the bounds")?;
+        check(self.turn_offset_hard.is_finite() && self.turn_offset_hard >= 0.0, "turn_offset_hard", "must not be negative")?;
+        check(self.turn_offset.is_finite() && self.turn_offset > self.turn_offset_hard, "turn_offset", "must be greater than turn_offset_hard")?;
+
+        check(self.alignment_gain.is_finite() && self.alignment_gain >= 0.0, "alignment_gain", "must not be negative")?;
+        check(self.tick_rate.is_finite() && self.tick_rate > 0.0, "tick_rate", "must be greater than zero")?;
+
+        check(self.speed.min.is_finite() && self.speed.min >= 0.0, "speed.min", "must not be negative")?;
+        check(self.speed.max.is_finite() && self.speed.max >= self.speed.min, "speed.max", "must be at least speed.min")?;
+
+        check(self.separation.speed_min.is_finite() && self.separation.speed_min >= 0.0, "separation.speed_min", "must not be negative")?;
+        check(self.separation.speed_max.is_finite() && self.separation.speed_max >= self.separation.speed_min, "separation.speed_max", "must be at least separation.speed_min")?;
+        check(self.separation.delta.is_finite() && self.separation.delta > 0.0, "separation.delta", "must be positive, separation turns birds away from their neighbours")?;
+
+        check(self.cohesion.speed_min.is_finite() && self.cohesion.speed_min >= 0.0, "cohesion.speed_min", "must not be negative")?;
+        check(self.cohesion.speed_max.is_finite() && self.cohesion.speed_max >= self.cohesion.speed_min, "cohesion.speed_max", "must be at least cohesion.speed_min")?;
+        check(self.cohesion.delta.is_finite() && self.cohesion.delta < 0.0, "cohesion.delta", "must be negative, cohesion turns birds towards their neighbours")?;
+
+        if let Neighbours::Topological{k} = self.neighbours{
+            check(k > 0, "neighbours.k", "must be at least one")?;
+        }
+
+        check(self.bird.region_radius.is_finite() && self.bird.region_radius > 0.0, "bird.region_radius", "must be greater than zero")?;
+        check(self.bird.separation_radius.is_finite() && self.bird.separation_radius > 0.0, "bird.separation_radius", "must be greater than zero")?;
+        check(self.bird.edge_bleed.is_finite() && self.bird.edge_bleed >= 0.0, "bird.edge_bleed", "must not be negative")?;
+        check(self.bird.turn_gain.is_finite() && self.bird.turn_gain > 0.0, "bird.turn_gain", "must be greater than zero")?;
+        check(self.bird.hard_angle_multiplier.is_finite() && self.bird.hard_angle_multiplier > 0.0, "bird.hard_angle_multiplier", "must be greater than zero")?;
+        check(self.bird.hard_angle_saturation.is_finite() && self.bird.hard_angle_saturation > 0.0, "bird.hard_angle_saturation", "must be greater than zero")?;
+        check(self.bird.distance_decay.is_finite() && self.bird.distance_decay >= 0.0, "bird.distance_decay", "must not be negative")?;
+        check(self.bird.field_of_view.is_finite() && self.bird.field_of_view > 0.0 && self.bird.field_of_view <= 360.0, "bird.field_of_view", "must be between 0 and 360 degrees")?;
+        check(self.bird.separation_field_of_view.is_finite() && self.bird.separation_field_of_view > 0.0 && self.bird.separation_field_of_view <= 360.0, "bird.separation_field_of_view", "must be between 0 and 360 degrees")?;
+        check(self.bird.obstacle_radius.is_finite() && self.bird.obstacle_radius > 0.0, "bird.obstacle_radius", "must be greater than zero")?;
+        check(self.bird.obstacle_gain.is_finite() && self.bird.obstacle_gain >= 0.0, "bird.obstacle_gain", "must not be negative")?;
+        check(self.bird.fear_radius.is_finite() && self.bird.fear_radius > 0.0, "bird.fear_radius", "must be greater than zero")?;
+        check(self.bird.fear_gain.is_finite() && self.bird.fear_gain >= 0.0, "bird.fear_gain", "must not be negative")?;
+        check(self.bird.pointer_radius.is_finite() && self.bird.pointer_radius > 0.0, "bird.pointer_radius", "must be greater than zero")?;
+        check(self.bird.pointer_gain.is_finite() && self.bird.pointer_gain >= 0.0, "bird.pointer_gain", "must not be negative")?;
+        check(self.bird.separation_weight.is_finite() && self.bird.separation_weight >= 0.0, "bird.separation_weight", "must not be negative")?;
+        check(self.bird.alignment_weight.is_finite() && self.bird.alignment_weight >= 0.0, "bird.alignment_weight", "must not be negative")?;
+        check(self.bird.cohesion_weight.is_finite() && self.bird.cohesion_weight >= 0.0, "bird.cohesion_weight", "must not be negative")?;
+        check(self.bird.max_force.is_finite() && self.bird.max_force > 0.0, "bird.max_force", "must be greater than zero")?;
+
+        check(self.predators.speed.is_finite() && self.predators.speed >= 0.0, "predators.speed", "must not be negative")?;
+        check(self.predators.turn_rate.is_finite() && self.predators.turn_rate >= 0.0, "predators.turn_rate", "must not be negative")?;
+
+        if let Some(containment) = &self.containment{
+            check(containment.validate(), "containment", "must have a positive size, polygons need at least three points enclosing some area")?;
+            check(self.boundary.turns(), "containment", "needs boundary = \"turn_box\", nothing else turns birds back")?;
+        }
+
+        for (i, obstacle) in self.obstacles.iter().enumerate(){
+            check(obstacle.validate(), "obstacles", &format!("obstacle {} must have a positive size, polygons must be convex with at least three points", i))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn bird_config(&self) -> BirdConfig{
+        BirdConfig{
+            separation: ProximitySettings::new(Speed::new(self.separation.speed_min, self.separation.speed_max, self.separation.randomise), self.separation.delta),
+            cohesion: ProximitySettings::new(Speed::new(self.cohesion.speed_min, self.cohesion.speed_max, self.cohesion.randomise), self.cohesion.delta),
+            alignment_gain: Gain::new(self.alignment_gain),
+            speed: Speed::new(self.speed.min, self.speed.max, self.speed.randomise),
+            tuning: Tuning{
+                region_radius: self.bird.region_radius,
+                separation_radius: self.bird.separation_radius,
+                edge_bleed: self.bird.edge_bleed,
+                turn_gain: self.bird.turn_gain,
+                hard_angle_multiplier: self.bird.hard_angle_multiplier,
+                hard_angle_saturation: self.bird.hard_angle_saturation,
+                distance_decay: self.bird.distance_decay,
+                field_of_view: self.bird.field_of_view,
+                separation_field_of_view: self.bird.separation_field_of_view,
+                obstacle_radius: self.bird.obstacle_radius,
+                obstacle_gain: self.bird.obstacle_gain,
+                fear_radius: self.bird.fear_radius,
+                fear_gain: self.bird.fear_gain,
+                pointer_radius: self.bird.pointer_radius,
+                pointer_gain: self.bird.pointer_gain,
+                separation_weight: self.bird.separation_weight,
+                alignment_weight: self.bird.alignment_weight,
+                cohesion_weight: self.bird.cohesion_weight,
+                max_force: self.bird.max_force,
+                separation_weighting: self.bird.separation_weighting,
+                cohesion_weighting: self.bird.cohesion_weighting,
+            },
+        }
+    }
+
+    /* Bounds from the file, or the default world size if it doesn't specify any */
+    pub fn world_bounds(&self) -> Rect<f32>{
+        let bounds = self.bounds.unwrap_or(BoundsSection::DEFAULT);
+        Rect::from_w_h(bounds.width, bounds.height)
+    }
+
+    /* Create the world and spawn the flock inside the turn box */
+    pub fn build(&self, bounds:Rect<f32>, seed:u64) -> World{
+        let mut world = World::new(bounds, self.bird_config(), self.seed.unwrap_or(seed));
+        world.set_turn_offsets(self.turn_offset, self.turn_offset_hard);
+        world.set_tick_rate(self.tick_rate);
+        world.set_neighbour_mode(self.neighbours);
+        world.set_steering_model(self.steering);
+        world.set_boundary(self.boundary);
+        world.set_obstacles(self.obstacles.iter().map(ObstacleSection::obstacle).collect());
+        world.set_containment(self.containment.as_ref().map(ContainmentSection::containment));
+
+        let area = world.spawn_area();
+        world.spawn(self.num_birds, &area);
+        world.spawn_predators(self.predators.count, self.predators.config(), &area);
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_field(result:Result<Scenario, ScenarioError>) -> &'static str{
+        match result{
+            Err(ScenarioError::Invalid(field, _)) => field,
+            _ => panic!("expected a validation error"),
+        }
+    }
+
+    fn parse_toml(text:&str) -> Result<Scenario, ScenarioError>{
+        let scenario:Scenario = toml::from_str(text).map_err(|err| ScenarioError::Parse("test".to_string(), err.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    #[test]
+    fn default_is_valid(){
+        assert!(Scenario::default().validate().is_ok());
+    }
+
+    #[test]
+    fn empty_file_is_default(){
+        assert_eq!(parse_toml("").unwrap(), Scenario::default());
+    }
+
+    #[test]
+    fn default_matches_bird_config(){
+        let config = Scenario::default().bird_config();
+        let expected = BirdConfig::new();
+
+        assert_eq!(config.separation.delta(), expected.separation.delta());
+        assert_eq!(config.cohesion.delta(), expected.cohesion.delta());
+        assert_eq!(config.alignment_gain.gain(), expected.alignment_gain.gain());
+        assert_eq!(config.speed.min(), expected.speed.min());
+        assert_eq!(config.speed.max(), expected.speed.max());
+        assert_eq!(config.tuning, expected.tuning);
+    }
+
+    #[test]
+    fn partial_toml(){
+        let scenario = parse_toml("
+            num_birds = 500
+            seed = 7
+
+            [bounds]
+            width = 3840.0
+            height = 2160.0
+
+            [bird]
+            region_radius = 100.0
+        ").unwrap();
+
+        assert_eq!(scenario.num_birds, 500);
+        assert_eq!(scenario.seed, Some(7));
+        assert_eq!(scenario.bounds, Some(BoundsSection{width: 3840.0, height: 2160.0}));
+        assert_eq!(scenario.world_bounds(), Rect::from_w_h(3840.0, 2160.0));
+        assert_eq!(Scenario::default().world_bounds(), Rect::from_w_h(1920.0, 1080.0));
+        assert_eq!(scenario.bird.region_radius, 100.0);
+        assert_eq!(scenario.bird.separation_radius, Tuning::new().separation_radius);
+    }
+
+    #[test]
+    fn json(){
+        let scenario:Scenario = serde_json::from_str("{\"num_birds\": 12, \"alignment_gain\": 0.5}").unwrap();
+        assert!(scenario.validate().is_ok());
+        assert_eq!(scenario.num_birds, 12);
+        assert_eq!(scenario.alignment_gain, 0.5);
+    }
+
+    #[test]
+    fn unknown_field_rejected(){
+        assert!(matches!(parse_toml("num_bird = 10"), Err(ScenarioError::Parse(_, _))));
+    }
+
+    #[test]
+    fn invalid_fields_named(){
+        assert_eq!(invalid_field(parse_toml("[separation]\nspeed_min = 1.0\nspeed_max = 2.0\nrandomise = true\ndelta = -0.1")), "separation.delta");
+        assert_eq!(invalid_field(parse_toml("[cohesion]\nspeed_min = 1.0\nspeed_max = 2.0\nrandomise = true\ndelta = 0.1")), "cohesion.delta");
+        assert_eq!(invalid_field(parse_toml("[speed]\nmin = 5.0\nmax = 2.0\nrandomise = true")), "speed.max");
+        assert_eq!(invalid_field(parse_toml("[bird]\nregion_radius = 0.0")), "bird.region_radius");
+        assert_eq!(invalid_field(parse_toml("[bird]\nhard_angle_saturation = 0.0")), "bird.hard_angle_saturation");
+        assert_eq!(invalid_field(parse_toml("[bird]\nfield_of_view = 400.0")), "bird.field_of_view");
+        assert_eq!(invalid_field(parse_toml("turn_offset = 10.0\nturn_offset_hard = 20.0")), "turn_offset");
+        assert_eq!(invalid_field(parse_toml("tick_rate = 0.0")), "tick_rate");
+        assert_eq!(invalid_field(parse_toml("[bounds]\nwidth = 400.0\nheight = 300.0")), "turn_offset");
+        assert_eq!(invalid_field(parse_toml("turn_offset = 600.0")), "turn_offset");
+    }
+
+    #[test]
+    fn round_trip(){
+        let scenario = Scenario{
+            seed: Some(99),
+            bounds: Some(BoundsSection{width: 800.0, height: 600.0}),
+            turn_offset: 100.0,
+            ..Scenario::default()
+        };
+
+        let text = toml::to_string(&scenario).unwrap();
+        assert_eq!(parse_toml(&text).unwrap(), scenario);
+    }
+
+    #[test]
+    fn settings_round_trip(){
+        let scenario = Scenario{
+            settings: Some(Settings{
+                show_radii: true,
+                show_turnbox: false,
+                show_trails: true,
+                show_debug: false,
+                pause: false,
+            }),
+            ..Scenario::default()
+        };
+
+        let text = toml::to_string(&scenario).unwrap();
+        assert_eq!(parse_toml(&text).unwrap(), scenario);
+
+        let text = serde_json::to_string(&scenario).unwrap();
+        assert_eq!(serde_json::from_str::<Scenario>(&text).unwrap(), scenario);
+    }
+
+    #[test]
+    fn obstacles(){
+        let scenario = parse_toml("
+            [[obstacles]]
+            shape = \"circle\"
+            x = 100.0
+            y = -50.0
+            radius = 40.0
+
+            [[obstacles]]
+            shape = \"rect\"
+            x = 0.0
+            y = 0.0
+            width = 200.0
+            height = 20.0
+
+            [[obstacles]]
+            shape = \"polygon\"
+            points = [[0.0, 0.0], [100.0, 0.0], [0.0, 100.0]]
+        ").unwrap();
+
+        assert_eq!(scenario.obstacles.len(), 3);
+        assert_eq!(scenario.obstacles[0], ObstacleSection::Circle{x: 100.0, y: -50.0, radius: 40.0});
+
+        let world = scenario.build(Rect::from_w_h(1920.0, 1080.0), 0);
+        assert_eq!(world.obstacles().len(), 3);
+        assert_eq!(world.obstacles()[0], Obstacle::circle(pt2(100.0, -50.0), 40.0));
+
+        let text = toml::to_string(&scenario).unwrap();
+        assert_eq!(parse_toml(&text).unwrap(), scenario);
+        assert_eq!(Scenario::from_world(&world).obstacles, scenario.obstacles);
+    }
+
+    #[test]
+    fn neighbours(){
+        let scenario = parse_toml("[neighbours]\nmode = \"topological\"\nk = 6").unwrap();
+        assert_eq!(scenario.neighbours, Neighbours::Topological{k: 6});
+        assert_eq!(scenario.build(Rect::from_w_h(1920.0, 1080.0), 0).neighbour_mode(), Neighbours::Topological{k: 6});
+
+        let scenario = parse_toml("[neighbours]\nmode = \"voronoi\"").unwrap();
+        assert_eq!(scenario.neighbours, Neighbours::Voronoi);
+
+        let text = toml::to_string(&scenario).unwrap();
+        assert_eq!(parse_toml(&text).unwrap(), scenario);
+
+        assert_eq!(invalid_field(parse_toml("[neighbours]\nmode = \"topological\"\nk = 0")), "neighbours.k");
+        assert!(matches!(parse_toml("[neighbours]\nmode = \"topological\""), Err(ScenarioError::Parse(_, _))));
+    }
+
+    #[test]
+    fn steering_model(){
+        let scenario = parse_toml("steering = \"vector\"\n[bird]\nmax_force = 200.0").unwrap();
+        assert_eq!(scenario.steering, SteeringModel::Vector);
+        assert_eq!(scenario.bird_config().tuning.max_force, 200.0);
+
+        let world = scenario.build(Rect::from_w_h(1920.0, 1080.0), 0);
+        assert_eq!(world.steering_model(), SteeringModel::Vector);
+        assert_eq!(Scenario::from_world(&world).steering, SteeringModel::Vector);
+
+        assert!(matches!(parse_toml("steering = \"sideways\""), Err(ScenarioError::Parse(_, _))));
+        assert_eq!(invalid_field(parse_toml("[bird]\nmax_force = 0.0")), "bird.max_force");
+        assert_eq!(invalid_field(parse_toml("[bird]\ncohesion_weight = -1.0")), "bird.cohesion_weight");
+    }
+
+    #[test]
+    fn boundary(){
+        let scenario = parse_toml("boundary = \"reflect\"").unwrap();
+        assert_eq!(scenario.boundary, Boundary::Reflect);
+        let world = scenario.build(Rect::from_w_h(1920.0, 1080.0), 0);
+        assert_eq!(world.boundary(), Boundary::Reflect);
+        assert_eq!(Scenario::from_world(&world).boundary, Boundary::Reflect);
+
+        assert_eq!(parse_toml("boundary = \"turn_box\"").unwrap().boundary, Boundary::TurnBox);
+        assert_eq!(parse_toml("boundary = \"unbounded\"").unwrap().boundary, Boundary::Unbounded);
+        assert!(matches!(parse_toml("boundary = \"sphere\""), Err(ScenarioError::Parse(_, _))));
+    }
+
+    #[test]
+    fn weighting(){
+        let scenario = parse_toml("[bird]\nseparation_weighting = \"inverse_square\"\ncohesion_weighting = \"linear\"").unwrap();
+        let tuning = scenario.bird_config().tuning;
+        assert_eq!(tuning.separation_weighting, Weighting::InverseSquare);
+        assert_eq!(tuning.cohesion_weighting, Weighting::Linear);
+
+        let text = toml::to_string(&scenario).unwrap();
+        assert_eq!(parse_toml(&text).unwrap(), scenario);
+        assert!(matches!(parse_toml("[bird]\ncohesion_weighting = \"cubic\""), Err(ScenarioError::Parse(_, _))));
+    }
+
+    #[test]
+    fn predators(){
+        let scenario = parse_toml("
+            [predators]
+            count = 2
+            pursuit = \"centroid\"
+        ").unwrap();
+
+        assert_eq!(scenario.predators.count, 2);
+        assert_eq!(scenario.predators.pursuit, Pursuit::Centroid);
+        assert_eq!(scenario.predators.speed, PredatorConfig::new().speed);
+
+        let world = scenario.build(Rect::from_w_h(1920.0, 1080.0), 0);
+        assert_eq!(world.predators().len(), 2);
+        assert_eq!(Scenario::from_world(&world).predators, scenario.predators);
+
+        assert_eq!(invalid_field(parse_toml("[predators]\nspeed = -1.0")), "predators.speed");
+        assert!(matches!(parse_toml("[predators]\npursuit = \"fastest\""), Err(ScenarioError::Parse(_, _))));
+    }
+
+    #[test]
+    fn containment(){
+        let scenario = parse_toml("
+            [containment]
+            shape = \"ellipse\"
+            x = 100.0
+            y = 0.0
+            width = 800.0
+            height = 400.0
+        ").unwrap();
+        assert_eq!(scenario.containment, Some(ContainmentSection::Ellipse{x: 100.0, y: 0.0, width: 800.0, height: 400.0}));
+
+        let world = scenario.build(Rect::from_w_h(1920.0, 1080.0), 0);
+        assert_eq!(world.containment(), Some(&Containment::ellipse(pt2(100.0, 0.0), vec2(400.0, 200.0))));
+        assert_eq!(Scenario::from_world(&world).containment, scenario.containment);
+
+        let text = toml::to_string(&scenario).unwrap();
+        assert_eq!(parse_toml(&text).unwrap(), scenario);
+
+        let scenario = parse_toml("[containment]\nshape = \"polygon\"\npoints = [[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [50.0, 20.0], [0.0, 100.0]]").unwrap();
+        assert!(matches!(scenario.containment, Some(ContainmentSection::Polygon{..})));
+
+        assert_eq!(invalid_field(parse_toml("[containment]\nshape = \"circle\"\nx = 0.0\ny = 0.0\nradius = -5.0")), "containment");
+        assert_eq!(invalid_field(parse_toml("[containment]\nshape = \"polygon\"\npoints = [[0.0, 0.0], [10.0, 0.0]]")), "containment");
+        assert_eq!(invalid_field(parse_toml("boundary = \"torus\"\n[containment]\nshape = \"circle\"\nx = 0.0\ny = 0.0\nradius = 300.0")), "containment");
+
+        /* Saving a world that has moved off the turn box leaves the unused containment out */
+        let mut world = world;
+        world.set_boundary(Boundary::Reflect);
+        assert_eq!(Scenario::from_world(&world).containment, None);
+    }
+
+    #[test]
+    fn invalid_obstacles(){
+        assert_eq!(invalid_field(parse_toml("[[obstacles]]\nshape = \"circle\"\nx = 0.0\ny = 0.0\nradius = 0.0")), "obstacles");
+        assert_eq!(invalid_field(parse_toml("[[obstacles]]\nshape = \"polygon\"\npoints = [[0.0, 0.0], [10.0, 0.0], [2.0, 2.0], [0.0, 10.0]]")), "obstacles");
+        assert!(matches!(parse_toml("[[obstacles]]\nshape = \"star\""), Err(ScenarioError::Parse(_, _))));
+    }
+
+    #[test]
+    fn from_world_keeps_tuning(){
+        let mut config = BirdConfig::new();
+        config.separation.inc_delta();
+        config.alignment_gain.decrement();
+        config.speed.inc_max();
+
+        let mut world = World::new(Rect::from_w_h(1280.0, 720.0), config, 11);
+        let area = world.inner();
+        world.spawn(10, &area);
+
+        let scenario = Scenario::from_world(&world);
+        assert!(scenario.validate().is_ok());
+        assert_eq!(scenario.num_birds, 10);
+        assert_eq!(scenario.seed, Some(11));
+        assert_eq!(scenario.bounds, Some(BoundsSection{width: 1280.0, height: 720.0}));
+        assert_eq!(scenario.world_bounds(), world.bounds());
+        assert_eq!(scenario.separation.delta, config.separation.delta());
+        assert_eq!(scenario.alignment_gain, config.alignment_gain.gain());
+        assert_eq!(scenario.speed.max, config.speed.max());
+    }
+
+    #[test]
+    fn build_world(){
+        let scenario = Scenario{
+            num_birds: 25,
+            seed: Some(3),
+            ..Scenario::default()
+        };
+
+        let world = scenario.build(Rect::from_w_h(1920.0, 1080.0), 0);
+        assert_eq!(world.birds().len(), 25);
+        assert_eq!(world.seed(), 3);
+    }
+}
@@ -1,5 +1,8 @@
 
-#[derive(Copy, Clone)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Settings{
     pub show_radii: bool,
     pub show_turnbox: bool,
@@ -0,0 +1,100 @@
+use crate::weighting::Weighting;
+
+/* Fine tuning of how a bird reacts to its neighbours and to the edge of the world */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tuning{
+    pub region_radius:f32,
+    pub separation_radius:f32,
+    pub edge_bleed:f32,
+    pub turn_gain:f32, // per second
+    pub hard_angle_multiplier:f32,
+    pub hard_angle_saturation:f32, // degrees per second
+    pub distance_decay:f32,
+    pub field_of_view:f32, // degrees
+    pub separation_field_of_view:f32, // degrees
+    pub obstacle_radius:f32,
+    pub obstacle_gain:f32, // per second
+    pub fear_radius:f32,
+    pub fear_gain:f32, // per second
+    pub pointer_radius:f32,
+    pub pointer_gain:f32, // per second
+    pub separation_weight:f32,
+    pub alignment_weight:f32,
+    pub cohesion_weight:f32,
+    pub max_force:f32, // per second squared
+    pub separation_weighting:Weighting,
+    pub cohesion_weighting:Weighting,
+}
+
+impl Tuning{
+    const BIRD_REGION_RADIUS:f32 = 225.0; 
+    const BIRD_SEPARATION_RADIUS:f32 = 30.0;
+
+    const EDGE_BLEED:f32 = 50.0;
+
+    /* Originally tuned per frame at 60 frames a second */
+    const TURN_GAIN:f32 = 0.020 * 60.0;
+
+    const HARD_ANGLE_MULTIPLIER:f32 = 5.0;
+    const HARD_ANGLE_SATURATION:f32 = 65.0 * 60.0;
+    
+    const DISTANCE_DECAY:f32 = 0.1;
+
+    /* Width of the cone a bird sees alignment/cohesion and separation neighbours in, 360 sees all round */
+    const FIELD_OF_VIEW:f32 = 360.0;
+    const SEPARATION_FIELD_OF_VIEW:f32 = 360.0;
+
+    /* How far ahead of an obstacle birds start to steer round it, and how hard */
+    const OBSTACLE_RADIUS:f32 = 150.0;
+    const OBSTACLE_GAIN:f32 = 12.0;
+
+    /* Distance at which birds notice a predator, and how hard they turn to flee it */
+    const FEAR_RADIUS:f32 = 250.0;
+    const FEAR_GAIN:f32 = 8.0;
+
+    /* Reach of the mouse pointer when attracting or repelling, and how hard birds turn for it */
+    const POINTER_RADIUS:f32 = 400.0;
+    const POINTER_GAIN:f32 = 4.0;
+
+    /* Vector steering model, how much each rule counts and the most a bird can accelerate */
+    const SEPARATION_WEIGHT:f32 = 1.5;
+    const ALIGNMENT_WEIGHT:f32 = 1.0;
+    const COHESION_WEIGHT:f32 = 1.0;
+    const MAX_FORCE:f32 = 300.0;
+
+    /* Every neighbour counts the same unless asked otherwise, as it always has */
+    const SEPARATION_WEIGHTING:Weighting = Weighting::Uniform;
+    const COHESION_WEIGHTING:Weighting = Weighting::Uniform;
+
+    pub fn new() -> Tuning{
+        Tuning{
+            region_radius: Self::BIRD_REGION_RADIUS,
+            separation_radius: Self::BIRD_SEPARATION_RADIUS,
+            edge_bleed: Self::EDGE_BLEED,
+            turn_gain: Self::TURN_GAIN,
+            hard_angle_multiplier: Self::HARD_ANGLE_MULTIPLIER,
+            hard_angle_saturation: Self::HARD_ANGLE_SATURATION,
+            distance_decay: Self::DISTANCE_DECAY,
+            field_of_view: Self::FIELD_OF_VIEW,
+            separation_field_of_view: Self::SEPARATION_FIELD_OF_VIEW,
+            obstacle_radius: Self::OBSTACLE_RADIUS,
+            obstacle_gain: Self::OBSTACLE_GAIN,
+            fear_radius: Self::FEAR_RADIUS,
+            fear_gain: Self::FEAR_GAIN,
+            pointer_radius: Self::POINTER_RADIUS,
+            pointer_gain: Self::POINTER_GAIN,
+            separation_weight: Self::SEPARATION_WEIGHT,
+            alignment_weight: Self::ALIGNMENT_WEIGHT,
+            cohesion_weight: Self::COHESION_WEIGHT,
+            max_force: Self::MAX_FORCE,
+            separation_weighting: Self::SEPARATION_WEIGHTING,
+            cohesion_weighting: Self::COHESION_WEIGHTING,
+        }
+    }
+}
+
+impl Default for Tuning{
+    fn default() -> Tuning{
+        Tuning::new()
+    }
+}
@@ -0,0 +1,168 @@
+use nannou::prelude::*;
+
+/* Maps world coordinates onto the window. The world is scaled to fit whatever size the
+ * window is, keeping its aspect ratio, and the rest of the window is letterboxed */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport{
+    world:Rect<f32>,
+    window:Rect<f32>,
+    centre:Point2,
+    scale:f32,
+}
+
+impl Viewport{
+    pub fn new(world:Rect<f32>, window:Rect<f32>) -> Viewport{
+        assert!(world.w() > 0.0 && world.h() > 0.0);
+        Viewport{
+            world,
+            window,
+            centre: world.xy(),
+            scale: (window.w() / world.w()).min(window.h() / world.h()),
+        }
+    }
+
+    /* Same scale, but looking at a different point in the world */
+    pub fn centred_on(self, centre:Point2) -> Viewport{
+        Viewport{
+            centre,
+            ..self
+        }
+    }
+
+    /* Magnified relative to fitting the world in the window */
+    pub fn zoomed(self, zoom:f32) -> Viewport{
+        assert!(zoom > 0.0);
+        Viewport{
+            scale: self.scale * zoom,
+            ..self
+        }
+    }
+
+    /* Window pixels per world unit */
+    pub fn scale(&self) -> f32{
+        self.scale
+    }
+
+    pub fn centre(&self) -> Point2{
+        self.centre
+    }
+
+    pub fn to_screen(&self, position:Point2) -> Point2{
+        self.window.xy() + ((position - self.centre) * self.scale)
+    }
+
+    pub fn to_world(&self, position:Point2) -> Point2{
+        self.centre + ((position - self.window.xy()) / self.scale)
+    }
+
+    /* Where the world is on screen, which can run past the window when zoomed in */
+    pub fn area(&self) -> Rect<f32>{
+        let centre = self.to_screen(self.world.xy());
+        Rect::from_x_y_w_h(centre.x, centre.y, self.world.w() * self.scale, self.world.h() * self.scale)
+    }
+
+    /* Parts of the window outside the world, empty when it fills the window */
+    pub fn bars(&self) -> Vec<Rect<f32>>{
+        let window = self.window;
+        let area = self.area();
+        let left = area.left().clamp(window.left(), window.right());
+        let right = area.right().clamp(window.left(), window.right());
+        let bottom = area.bottom().clamp(window.bottom(), window.top());
+        let top = area.top().clamp(window.bottom(), window.top());
+
+        /* Full height down either side, then whatever is left above and below */
+        let bars = [
+            (window.left(), left, window.bottom(), window.top()),
+            (right, window.right(), window.bottom(), window.top()),
+            (left, right, top, window.top()),
+            (left, right, window.bottom(), bottom),
+        ];
+
+        /* Anything under half a pixel is just rounding */
+        bars.iter()
+            .filter(|(left, right, bottom, top)| right - left > 0.5 && top - bottom > 0.5)
+            .map(|(left, right, bottom, top)| Rect::from_x_y_w_h((left + right) / 2.0, (bottom + top) / 2.0, right - left, top - bottom))
+            .collect()
+    }
+
+    /* Draw in world coordinates */
+    pub fn transform(&self, draw:&Draw) -> Draw{
+        draw.xy(self.window.xy())
+            .scale(self.scale)
+            .xy(-self.centre)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const FLOAT_PRECISION:f32 = 0.0001;
+
+    fn world() -> Rect<f32>{
+        Rect::from_w_h(1920.0, 1080.0)
+    }
+
+    #[test]
+    fn same_size(){
+        let viewport = Viewport::new(world(), world());
+        assert_eq!(viewport.scale(), 1.0);
+        assert_eq!(viewport.to_screen(pt2(100.0, -50.0)), pt2(100.0, -50.0));
+        assert!(viewport.bars().is_empty());
+    }
+
+    #[test]
+    fn scales_up(){
+        /* 4K fits exactly at twice the size */
+        let viewport = Viewport::new(world(), Rect::from_w_h(3840.0, 2160.0));
+        assert_eq!(viewport.scale(), 2.0);
+        assert_eq!(viewport.to_screen(pt2(960.0, 540.0)), pt2(1920.0, 1080.0));
+        assert_eq!(viewport.to_world(pt2(-1920.0, 0.0)), pt2(-960.0, 0.0));
+        assert!(viewport.bars().is_empty());
+    }
+
+    #[test]
+    fn letterbox(){
+        /* Ultrawide, so bars either side */
+        let viewport = Viewport::new(world(), Rect::from_w_h(3440.0, 1440.0));
+        let scale = 1440.0 / 1080.0;
+        assert!((viewport.scale() - scale).abs() < FLOAT_PRECISION);
+        assert!((viewport.area().w() - (1920.0 * scale)).abs() < 0.01);
+
+        let bars = viewport.bars();
+        assert_eq!(bars.len(), 2);
+        assert!((bars[0].left() + 1720.0).abs() < 0.01);
+        assert!((bars[0].right() - viewport.area().left()).abs() < 0.01);
+        assert!((bars[1].right() - 1720.0).abs() < 0.01);
+
+        /* Tall and narrow, so bars above and below */
+        let viewport = Viewport::new(world(), Rect::from_w_h(960.0, 1080.0));
+        assert_eq!(viewport.scale(), 0.5);
+        let bars = viewport.bars();
+        assert_eq!(bars.len(), 2);
+        assert!((bars[0].h() - 270.0).abs() < FLOAT_PRECISION);
+    }
+
+    #[test]
+    fn zoom(){
+        let viewport = Viewport::new(world(), world()).zoomed(2.0);
+        assert_eq!(viewport.scale(), 2.0);
+        assert!(viewport.bars().is_empty());
+
+        /* Zoomed out and looking off to the right, so the world sits left of centre */
+        let viewport = Viewport::new(world(), world()).zoomed(0.5).centred_on(pt2(480.0, 0.0));
+        assert_eq!(viewport.area(), Rect::from_x_y_w_h(-240.0, 0.0, 960.0, 540.0));
+        assert_eq!(viewport.bars().len(), 4);
+        assert_eq!(viewport.bars()[0], Rect::from_x_y_w_h(-840.0, 0.0, 240.0, 1080.0));
+        assert_eq!(viewport.bars()[1], Rect::from_x_y_w_h(600.0, 0.0, 720.0, 1080.0));
+    }
+
+    #[test]
+    fn round_trip(){
+        let viewport = Viewport::new(world(), Rect::from_w_h(1280.0, 1024.0)).centred_on(pt2(300.0, -200.0));
+        assert_eq!(viewport.to_screen(pt2(300.0, -200.0)), pt2(0.0, 0.0));
+
+        let position = pt2(123.0, 456.0);
+        let back = viewport.to_world(viewport.to_screen(position));
+        assert!(back.distance(position) < 0.001);
+    }
+}
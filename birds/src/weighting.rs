@@ -0,0 +1,88 @@
+use serde::{Serialize, Deserialize};
+
+/* How much a neighbour counts towards separation or cohesion depending on how far away it is */
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Weighting{
+    /* Every neighbour counts the same */
+    #[default]
+    Uniform,
+    /* 1 / distance */
+    Inverse,
+    /* 1 / distance squared */
+    InverseSquare,
+    /* Falls from 1 right next to the bird to 0 at the edge of its radius */
+    Linear,
+}
+
+impl Weighting{
+    /* Closer than this counts as this close, so overlapping birds don't weigh infinitely much */
+    const MIN_DISTANCE:f32 = 1.0;
+
+    /* Most a single neighbour can scale a reaction by, however close it is */
+    const MAX_STRENGTH:f32 = 4.0;
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            Weighting::Uniform => "Uniform",
+            Weighting::Inverse => "Inverse",
+            Weighting::InverseSquare => "Inverse Square",
+            Weighting::Linear => "Linear",
+        }
+    }
+
+    /* Weight of a neighbour at a distance, for a rule reaching out to radius */
+    pub fn weight(&self, distance:f32, radius:f32) -> f32{
+        let distance = distance.max(Self::MIN_DISTANCE);
+        match self{
+            Weighting::Uniform => 1.0,
+            Weighting::Inverse => 1.0 / distance,
+            Weighting::InverseSquare => 1.0 / (distance * distance),
+            Weighting::Linear => (1.0 - (distance / radius)).max(0.0),
+        }
+    }
+
+    /* How strongly to react to a neighbour at a distance, relative to one half way out to
+     * the radius. Uniform is always 1, so the reaction is the same wherever it is */
+    pub fn strength(&self, distance:f32, radius:f32) -> f32{
+        (self.weight(distance, radius) / self.weight(radius / 2.0, radius)).min(Self::MAX_STRENGTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const FLOAT_PRECISION:f32 = 0.00001;
+
+    #[test]
+    fn nearer_weighs_more(){
+        for weighting in [Weighting::Inverse, Weighting::InverseSquare, Weighting::Linear]{
+            assert!(weighting.weight(5.0, 30.0) > weighting.weight(20.0, 30.0), "{}", weighting.name());
+        }
+        assert_eq!(Weighting::Uniform.weight(5.0, 30.0), Weighting::Uniform.weight(20.0, 30.0));
+
+        assert!((Weighting::Inverse.weight(4.0, 30.0) - 0.25).abs() < FLOAT_PRECISION);
+        assert!((Weighting::InverseSquare.weight(4.0, 30.0) - 0.0625).abs() < FLOAT_PRECISION);
+        assert!((Weighting::Linear.weight(15.0, 30.0) - 0.5).abs() < FLOAT_PRECISION);
+    }
+
+    #[test]
+    fn limits(){
+        /* Overlapping birds still give a finite weight */
+        assert_eq!(Weighting::InverseSquare.weight(0.0, 30.0), 1.0);
+        assert_eq!(Weighting::Linear.weight(45.0, 30.0), 0.0);
+    }
+
+    #[test]
+    fn strength(){
+        assert_eq!(Weighting::Uniform.strength(1.0, 30.0), 1.0);
+        assert_eq!(Weighting::Uniform.strength(29.0, 30.0), 1.0);
+
+        assert!((Weighting::Inverse.strength(15.0, 30.0) - 1.0).abs() < FLOAT_PRECISION);
+        assert!((Weighting::Inverse.strength(7.5, 30.0) - 2.0).abs() < FLOAT_PRECISION);
+        assert!((Weighting::Linear.strength(30.0, 30.0)).abs() < FLOAT_PRECISION);
+
+        /* Capped right up close */
+        assert_eq!(Weighting::InverseSquare.strength(0.0, 30.0), Weighting::MAX_STRENGTH);
+    }
+}
@@ -0,0 +1,1129 @@
+use std::borrow::Cow;
+
+use nannou::prelude::*;
+
+use crate::bird::Bird;
+use crate::bird::BirdConfig;
+use crate::bird::TRAIL_LEN;
+use crate::meta::Meta;
+use crate::calcs;
+use crate::rng::Rng;
+use crate::grid::Grid;
+use crate::metrics::Metrics;
+use crate::history::{History, Snapshot};
+use crate::obstacle::{Obstacle, Avoidance};
+use crate::predator::{Predator, PredatorConfig};
+use crate::pointer::Pointer;
+use crate::neighbours::Neighbours;
+use crate::reynolds::{self, SteeringModel};
+use crate::boundary::Boundary;
+use crate::containment::{Containment, Edge};
+
+/* Headless flock simulation, owns the birds and advances them one step at a time */
+pub struct World{
+    bird:Vec<Bird>,
+    bird_config:BirdConfig,
+    predator:Vec<Predator>,
+    bounds:Rect<f32>,
+    meta:Meta,
+    metrics:Metrics,
+    seed:u64,
+    rng:Rng,
+    grid:Grid,
+    obstacles:Vec<Obstacle>,
+    pointer:Option<Pointer>,
+    neighbours:Neighbours,
+    steering_model:SteeringModel,
+    boundary:Boundary,
+    containment:Option<Containment>,
+    turn_offset:f32,
+    turn_offset_hard:f32,
+    tick_rate:f32,
+    accumulator:f32,
+    history:History,
+    #[cfg(feature = "parallel")]
+    parallel:bool,
+}
+
+impl World{
+    pub const TURN_OFFSET:f32 = 250.0;
+    pub const TURN_OFFSET_HARD:f32 = 80.0;
+    pub const TICK_RATE:f32 = 60.0;
+
+    /* Most ticks caught up on in one go at 1x, beyond this the lost time is dropped
+     * so a slow machine doesn't fall further and further behind */
+    const MAX_TICKS:u32 = 8;
+
+    /* Most memory the rewind history can take up, big flocks get fewer ticks of it */
+    pub const REWIND_BUDGET:usize = 64 * 1024 * 1024;
+
+    /* Most cells across an unbounded world's grid, birds further out than this share the edge cells */
+    const MAX_GRID_CELLS:f32 = 256.0;
+
+    pub fn new(bounds:Rect<f32>, bird_config:BirdConfig, seed:u64) -> World{
+        World{
+            bird: Vec::new(),
+            bird_config,
+            predator: Vec::new(),
+            bounds,
+            meta: Meta::new(),
+            metrics: Metrics::new(),
+            seed,
+            rng: Rng::new(seed),
+            grid: Grid::new(bounds.pad(-bird_config.tuning.edge_bleed), bird_config.tuning.region_radius, false),
+            obstacles: Vec::new(),
+            pointer: None,
+            neighbours: Neighbours::Metric,
+            steering_model: SteeringModel::Angular,
+            boundary: Boundary::TurnBox,
+            containment: None,
+            turn_offset: Self::TURN_OFFSET,
+            turn_offset_hard: Self::TURN_OFFSET_HARD,
+            tick_rate: Self::TICK_RATE,
+            accumulator: 0.0,
+            history: History::new(0),
+            #[cfg(feature = "parallel")]
+            parallel: true,
+        }
+    }
+
+    /* Spawn birds at random positions within the area, facing a random direction */
+    pub fn spawn(&mut self, num_birds:u32, area:&Rect<f32>){
+        for _i in 0..num_birds{
+            let x = self.rng.range(area.left(), area.right());
+            let y = self.rng.range(area.bottom(), area.top());
+            let angle = self.rng.range(0.0, 359.0);
+
+            self.bird.push(Bird::new(pt2(x, y), deg_to_rad(angle), self.bird_config));
+        }
+    }
+
+    /* Spawn a group of birds scattered around a point, all heading the same random way */
+    pub fn spawn_group(&mut self, num_birds:u32, centre:Point2, spread:f32){
+        let angle = self.rng.range(0.0, 359.0);
+        for _i in 0..num_birds{
+            let x = centre.x + self.rng.range(-spread, spread);
+            let y = centre.y + self.rng.range(-spread, spread);
+
+            self.bird.push(Bird::new(pt2(x, y), deg_to_rad(angle), self.bird_config));
+        }
+    }
+
+    pub fn birds(&self) -> &Vec<Bird>{
+        &self.bird
+    }
+
+    /* Index of the bird closest to a point, if any are within the radius, e.g. to pick one out with the mouse */
+    pub fn nearest_bird(&self, position:Point2, radius:f32) -> Option<usize>{
+        self.bird.iter()
+            .enumerate()
+            .map(|(i, bird)| (i, bird.position().distance(position)))
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /* Spawn predators at random positions within the area, they hunt from the next step */
+    pub fn spawn_predators(&mut self, num_predators:u32, config:PredatorConfig, area:&Rect<f32>){
+        for _i in 0..num_predators{
+            let x = self.rng.range(area.left(), area.right());
+            let y = self.rng.range(area.bottom(), area.top());
+            let angle = self.rng.range(0.0, 359.0);
+
+            self.predator.push(Predator::new(pt2(x, y), deg_to_rad(angle), config));
+        }
+    }
+
+    pub fn predators(&self) -> &Vec<Predator>{
+        &self.predator
+    }
+
+    pub fn config(&self) -> &BirdConfig{
+        &self.bird_config
+    }
+
+    pub fn set_config(&mut self, config:BirdConfig){
+        self.bird_config = config;
+        for bird in &mut self.bird{
+            bird.refresh_settings(&mut self.bird_config);
+        }
+    }
+
+    pub fn bounds(&self) -> Rect<f32>{
+        self.bounds
+    }
+
+    pub fn set_bounds(&mut self, bounds:Rect<f32>){
+        self.bounds = bounds;
+    }
+
+    pub fn obstacles(&self) -> &Vec<Obstacle>{
+        &self.obstacles
+    }
+
+    pub fn set_obstacles(&mut self, obstacles:Vec<Obstacle>){
+        self.obstacles = obstacles;
+    }
+
+    pub fn pointer(&self) -> Option<Pointer>{
+        self.pointer
+    }
+
+    /* Attract or repel the flock from a point, None to let it go */
+    pub fn set_pointer(&mut self, pointer:Option<Pointer>){
+        self.pointer = pointer;
+    }
+
+    pub fn neighbour_mode(&self) -> Neighbours{
+        self.neighbours
+    }
+
+    /* How birds pick which of the flock to react to */
+    pub fn set_neighbour_mode(&mut self, neighbours:Neighbours){
+        self.neighbours = neighbours;
+    }
+
+    pub fn steering_model(&self) -> SteeringModel{
+        self.steering_model
+    }
+
+    /* How the flocking rules turn each bird */
+    pub fn set_steering_model(&mut self, steering_model:SteeringModel){
+        self.steering_model = steering_model;
+    }
+
+    pub fn toggle_steering_model(&mut self){
+        self.steering_model = self.steering_model.toggle();
+    }
+
+    pub fn boundary(&self) -> Boundary{
+        self.boundary
+    }
+
+    /* What birds do at the edge of the bounds */
+    pub fn set_boundary(&mut self, boundary:Boundary){
+        self.boundary = boundary;
+    }
+
+    pub fn containment(&self) -> Option<&Containment>{
+        self.containment.as_ref()
+    }
+
+    /* Shape birds turn back into rather than the bounds, only while the boundary turns them */
+    pub fn set_containment(&mut self, containment:Option<Containment>){
+        self.containment = containment;
+    }
+
+    /* Somewhere birds can be spawned without having to turn back straight away */
+    pub fn spawn_area(&self) -> Rect<f32>{
+        match &self.containment{
+            Some(containment) if self.boundary.turns() => containment.bounding_rect(),
+            _ => self.inner(),
+        }
+    }
+
+    /* Distance from the bounds at which birds start turning back, and turn back harder */
+    pub fn set_turn_offsets(&mut self, turn_offset:f32, turn_offset_hard:f32){
+        self.turn_offset = turn_offset;
+        self.turn_offset_hard = turn_offset_hard;
+    }
+
+    pub fn turn_offset(&self) -> f32{
+        self.turn_offset
+    }
+
+    pub fn turn_offset_hard(&self) -> f32{
+        self.turn_offset_hard
+    }
+
+    /* Number of fixed steps per second of simulated time */
+    pub fn set_tick_rate(&mut self, tick_rate:f32){
+        assert!(tick_rate > 0.0);
+        self.tick_rate = tick_rate;
+    }
+
+    pub fn tick_rate(&self) -> f32{
+        self.tick_rate
+    }
+
+    /* Simulated time covered by a single step, in seconds */
+    pub fn dt(&self) -> f32{
+        1.0 / self.tick_rate
+    }
+
+    pub fn meta(&self) -> &Meta{
+        &self.meta
+    }
+
+    /* Simulated seconds per real second, see Meta for the range */
+    pub fn set_time_scale(&mut self, time_scale:f32){
+        self.meta.set_time_scale(time_scale);
+    }
+
+    pub fn speed_up(&mut self){
+        self.meta.faster();
+    }
+
+    pub fn slow_down(&mut self){
+        self.meta.slower();
+    }
+
+    pub fn metrics(&self) -> &Metrics{
+        &self.metrics
+    }
+
+    pub fn seed(&self) -> u64{
+        self.seed
+    }
+
+    /* Region inside which birds fly freely, outside of it they start turning back */
+    pub fn inner(&self) -> Rect<f32>{
+        self.bounds.pad(self.turn_offset)
+    }
+
+    pub fn inner_hard(&self) -> Rect<f32>{
+        self.bounds.pad(self.turn_offset_hard)
+    }
+
+    fn rebuild_grid(&mut self){
+        let (extent, wrap) = self.grid_extent();
+        self.grid.rebuild(extent, self.bird_config.tuning.region_radius, wrap, &self.bird);
+    }
+
+    /* Birds are allowed to bleed past the bounds before wrapping, so the grid covers that too.
+     * A torus is covered exactly and searched across its edges, an unbounded world wherever the flock is */
+    fn grid_extent(&self) -> (Rect<f32>, bool){
+        let tuning = self.bird_config.tuning;
+        match self.boundary{
+            Boundary::Torus => (self.bounds, true),
+            Boundary::Unbounded => (self.flock_extent(tuning.region_radius), false),
+            Boundary::TurnBox | Boundary::Reflect => (self.bounds.pad(-tuning.edge_bleed), false),
+        }
+    }
+
+    /* The birds bird i aligns and coheres with, and those it separates from, e.g. to inspect it.
+     * Found from where the flock is now, so birds spawned since the last step are included */
+    pub fn neighbours(&self, i:usize) -> (Vec<usize>, Vec<usize>){
+        let (extent, wrap) = self.grid_extent();
+        let cell_size = self.bird_config.tuning.region_radius;
+        let mut grid = Grid::new(extent, cell_size, wrap);
+        grid.rebuild(extent, cell_size, wrap, &self.bird);
+
+        let mut nearby = Vec::new();
+        let mut nearby_sep = Vec::new();
+        self.neighbours.select(&grid, &self.bird, i, &mut nearby, &mut nearby_sep);
+        (nearby, nearby_sep)
+    }
+
+    /* Shortest way from one point to another, which on a torus can be across the edge */
+    pub fn offset(&self, position:Point2, other:Point2) -> Vec2{
+        self.grid.offset(position, other)
+    }
+
+    /* Bounds stretched to take in every bird, rounded out to whole cells so the grid
+     * isn't re-sized every step. A few stragglers can end up a long way off, so it
+     * is cut down to a limited number of cells around the middle of the flock */
+    fn flock_extent(&self, cell_size:f32) -> Rect<f32>{
+        let mut min = pt2(self.bounds.left(), self.bounds.bottom());
+        let mut max = pt2(self.bounds.right(), self.bounds.top());
+        for bird in &self.bird{
+            min = min.min(bird.position());
+            max = max.max(bird.position());
+        }
+
+        let mut min = (min / cell_size).floor() * cell_size;
+        let mut max = (max / cell_size).ceil() * cell_size;
+        if !self.bird.is_empty(){
+            let centre = (calcs::average_position(&self.bird) / cell_size).round() * cell_size;
+            let span = Self::MAX_GRID_CELLS * cell_size * 0.5;
+            min = min.max(centre - span);
+            max = max.min(centre + span);
+        }
+        Rect::from_x_y_w_h((min.x + max.x) / 2.0, (min.y + max.y) / 2.0, max.x - min.x, max.y - min.y)
+    }
+
+    /* Keep the last few ticks so they can be stepped back through, none by default */
+    pub fn set_rewind(&mut self, ticks:usize){
+        self.history = History::new(ticks);
+        self.remember();
+    }
+
+    /* How many ticks the world has been stepped back from the latest one */
+    pub fn rewound(&self) -> usize{
+        self.history.rewound()
+    }
+
+    pub fn step_back(&mut self) -> bool{
+        if self.history.back().is_none(){
+            return false;
+        }
+        self.restore();
+        true
+    }
+
+    pub fn step_forward(&mut self) -> bool{
+        if self.history.forward().is_none(){
+            return false;
+        }
+        self.restore();
+        true
+    }
+
+    fn snapshot(&self) -> Snapshot{
+        Snapshot{
+            bird: self.bird.iter().map(|b| b.snapshot()).collect(),
+            predator: self.predator.clone(),
+            rng: self.rng,
+            meta: self.meta,
+            metrics: self.metrics,
+        }
+    }
+
+    /* Keep the world as it is now in the history, trimming it to fit the memory budget */
+    fn remember(&mut self){
+        let snapshot = self.snapshot();
+        let budget = Self::REWIND_BUDGET / snapshot.size();
+        self.history.push(snapshot);
+        self.history.limit(budget);
+    }
+
+    /* Go back to the snapshot at the history's cursor, each bird's trail running through
+     * its positions in the snapshots before. The time scale is a viewing choice rather
+     * than part of the flock, so it's left alone */
+    fn restore(&mut self){
+        let recent:Vec<&Snapshot> = self.history.recent(TRAIL_LEN).collect();
+        let snapshot = *recent.last().unwrap();
+
+        self.bird = snapshot.bird.iter()
+            .enumerate()
+            .map(|(i, bird)|{
+                let trail = recent.iter().filter_map(|s| s.bird.get(i)).map(|b| b.position());
+                Bird::restore(bird, trail, self.bird_config)
+            })
+            .collect();
+
+        let time_scale = self.meta.time_scale();
+        self.predator = snapshot.predator.clone();
+        self.rng = snapshot.rng;
+        self.meta = snapshot.meta;
+        self.meta.set_time_scale(time_scale);
+        self.metrics = snapshot.metrics;
+    }
+
+    /* Add real time, scaled by the time scale, to the accumulator and take out as many
+     * whole ticks as it holds, the caller then steps the world that many times */
+    pub fn accumulate(&mut self, elapsed:f32) -> u32{
+        let dt = self.dt();
+        let time_scale = self.meta.time_scale();
+        let max_ticks = (Self::MAX_TICKS as f32 * time_scale.max(1.0)).ceil() as u32;
+        self.accumulator += elapsed * time_scale;
+
+        let mut ticks = 0;
+        while self.accumulator >= dt{
+            if ticks == max_ticks{
+                self.accumulator = 0.0;
+                break;
+            }
+            self.accumulator -= dt;
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /* Step the world for however many ticks fit into the elapsed time, so it runs
+     * at the same rate whatever the frame rate */
+    pub fn advance(&mut self, elapsed:f32) -> u32{
+        let ticks = self.accumulate(elapsed);
+        for _i in 0..ticks{
+            self.step();
+        }
+        ticks
+    }
+
+    pub fn step(&mut self){
+        let dt = self.dt();
+        let boundary = self.boundary;
+        let win = self.bounds;
+        let inner = self.inner();
+        let inner_hard = self.inner_hard();
+
+        /* 1. Work out how every bird wants to steer from a snapshot of the flock */
+        self.rebuild_grid();
+        let steering = self.steering();
+
+        /* 2. Apply it, in order, so the random draws are always the same for a given seed */
+        for (bird, steer) in self.bird.iter_mut().zip(steering.iter()){
+            steer.apply(bird);
+            bird.update(boundary, &win, &inner, &inner_hard, dt, &mut self.rng);
+        }
+
+        /* Predators chase the flock where it has just moved to */
+        for predator in &mut self.predator{
+            predator.update(&self.bird, boundary, &win, dt);
+        }
+
+        /* 3. Measure the flock where it has ended up */
+        self.rebuild_grid();
+        self.metrics = Metrics::measure(&self.bird, &self.grid);
+
+        self.meta.update(dt);
+
+        if self.history.capacity() > 0{
+            self.remember();
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn set_parallel(&mut self, parallel:bool){
+        self.parallel = parallel;
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn steering(&self) -> Vec<Steering>{
+        self.steering_serial()
+    }
+
+    #[cfg(feature = "parallel")]
+    fn steering(&self) -> Vec<Steering>{
+        if self.parallel{
+            self.steering_parallel()
+        }
+        else{
+            self.steering_serial()
+        }
+    }
+
+    fn steering_serial(&self) -> Vec<Steering>{
+        let mut nearby:Vec<usize> = Vec::new();
+        let mut nearby_sep:Vec<usize> = Vec::new();
+
+        (0..self.bird.len())
+            .map(|i| Steering::new(self, i, &mut nearby, &mut nearby_sep))
+            .collect()
+    }
+
+    #[cfg(feature = "parallel")]
+    fn steering_parallel(&self) -> Vec<Steering>{
+        use rayon::prelude::*;
+
+        (0..self.bird.len())
+            .into_par_iter()
+            .map_init(|| (Vec::new(), Vec::new()), |(nearby, nearby_sep), i|{
+                Steering::new(self, i, nearby, nearby_sep)
+            })
+            .collect()
+    }
+}
+
+/* Result of the separation, alignment, cohesion, obstacle, predator and pointer rules for a single bird */
+#[derive(Copy, Clone)]
+struct Steering{
+    separation:Option<(f32, f32, f32)>,
+    alignment:f32,
+    cohesion:Option<(f32, f32, f32)>,
+    avoidance:Option<Avoidance>,
+    threat:Option<Avoidance>,
+    lure:Option<Avoidance>,
+    force:Option<Vec2>,
+    edge:Option<Edge>,
+}
+
+impl Steering{
+    fn new(world:&World, i:usize, nearby:&mut Vec<usize>, nearby_sep:&mut Vec<usize>) -> Steering{
+        let bird = &world.bird;
+        let mut steering = Steering{
+            separation: None,
+            alignment: 0.0,
+            cohesion: None,
+            avoidance: None,
+            threat: None,
+            lure: None,
+            force: None,
+            edge: None,
+        };
+
+        /* Collect nearby birds */
+        let position = bird[i].position();
+        world.neighbours.select(&world.grid, bird, i, nearby, nearby_sep);
+
+        if world.grid.wrap(){
+            /* On a torus a neighbour across the edge is seen where it would be if the world carried on */
+            let image = |j:usize| -> Cow<Bird>{
+                let offset = world.grid.offset(position, bird[j].position());
+                if offset == bird[j].position() - position{
+                    Cow::Borrowed(&bird[j])
+                }
+                else{
+                    Cow::Owned(bird[j].moved_to(position + offset))
+                }
+            };
+            let sep_birds:Vec<Cow<Bird>> = nearby_sep.iter().map(|&j| image(j)).collect();
+            let flock_birds:Vec<Cow<Bird>> = nearby.iter().map(|&j| image(j)).collect();
+            steering.flock(world.steering_model, &bird[i], sep_birds.iter().map(|b| &**b), flock_birds.iter().map(|b| &**b));
+        }
+        else{
+            steering.flock(world.steering_model, &bird[i], nearby_sep.iter().map(|&j| &bird[j]), nearby.iter().map(|&j| &bird[j]));
+        }
+
+        /* Handle Obstacles */
+        steering.avoidance = Obstacle::nearest(&world.obstacles, position, bird[i].obstacle_radius());
+
+        /* Handle Predators */
+        steering.threat = Predator::nearest(&world.predator, position, bird[i].fear_radius());
+
+        /* Handle the Pointer */
+        steering.lure = world.pointer.and_then(|p| p.lure(position, bird[i].pointer_radius()));
+
+        /* Handle the Containment */
+        if world.boundary.turns(){
+            steering.edge = world.containment.as_ref().map(|c| c.edge(position, world.turn_offset, world.turn_offset_hard));
+        }
+
+        steering
+    }
+
+    /* Separation, alignment and cohesion from the birds each rule can see */
+    fn flock<'a, I, J>(&mut self, steering_model:SteeringModel, bird:&Bird, sep_birds:I, flock_birds:J)
+        where I: Iterator<Item = &'a Bird> + Clone,
+              J: Iterator<Item = &'a Bird> + Clone
+    {
+        match steering_model{
+            SteeringModel::Angular => {
+                /* Handle Separation */
+                if sep_birds.clone().next().is_some(){
+                    self.separation = Some(calcs::separation(bird, sep_birds));
+                }
+
+                /* Handle Alignment */
+                if flock_birds.clone().next().is_some(){
+                    self.alignment = calcs::alignment(bird, flock_birds.clone());
+
+                    /* Handle Cohesion */
+                    self.cohesion = Some(calcs::cohesion(bird, flock_birds));
+                }
+            },
+            SteeringModel::Vector => {
+                /* All three rules as one force */
+                self.force = Some(reynolds::force(bird, sep_birds, flock_birds));
+            },
+        }
+    }
+
+    fn apply(&self, bird:&mut Bird){
+        if let Some(sep_angle) = self.separation{
+            bird.set_separation(sep_angle.0, sep_angle.1, sep_angle.2);
+        }
+
+        bird.set_alignment(self.alignment);
+
+        if let Some(coh_angle) = self.cohesion{
+            bird.set_cohesion(coh_angle.0, coh_angle.1, coh_angle.2);
+        }
+
+        bird.set_avoidance(self.avoidance);
+        bird.set_threat(self.threat);
+        bird.set_lure(self.lure);
+        bird.set_force(self.force);
+        bird.set_edge(self.edge);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::angle;
+    use crate::bird::State;
+
+    fn test_bounds() -> Rect<f32>{
+        Rect::from_w_h(1920.0, 1080.0)
+    }
+
+    #[test]
+    fn new_world_is_empty(){
+        let world = World::new(test_bounds(), BirdConfig::new(), 1);
+        assert_eq!(world.birds().len(), 0);
+        assert_eq!(world.meta().iterations(), 0);
+    }
+
+    #[test]
+    fn spawn_within_area(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 1);
+        let area = world.inner();
+        world.spawn(50, &area);
+
+        assert_eq!(world.birds().len(), 50);
+        for bird in world.birds(){
+            assert!(bird.position().x >= area.left() && bird.position().x <= area.right());
+            assert!(bird.position().y >= area.bottom() && bird.position().y <= area.top());
+        }
+    }
+
+    #[test]
+    fn nearest_bird(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 1);
+        world.spawn_group(1, pt2(0.0, 0.0), 0.0);
+        world.spawn_group(1, pt2(100.0, 0.0), 0.0);
+
+        assert_eq!(world.nearest_bird(pt2(60.0, 10.0), 50.0), Some(1));
+        assert_eq!(world.nearest_bird(pt2(40.0, 10.0), 50.0), Some(0));
+        assert_eq!(world.nearest_bird(pt2(50.0, 200.0), 50.0), None);
+    }
+
+    #[test]
+    fn step_counts_iterations(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 1);
+        let area = world.inner();
+        world.spawn(20, &area);
+
+        for _i in 0..100{
+            world.step();
+        }
+        assert_eq!(world.meta().iterations(), 100);
+        assert_eq!(world.birds().len(), 20);
+    }
+
+    #[test]
+    fn same_seed_same_trajectories(){
+        let mut world_a = World::new(test_bounds(), BirdConfig::new(), 5678);
+        let mut world_b = World::new(test_bounds(), BirdConfig::new(), 5678);
+        let area = world_a.inner();
+        world_a.spawn(40, &area);
+        world_b.spawn(40, &area);
+
+        for _i in 0..500{
+            world_a.step();
+            world_b.step();
+        }
+
+        for (a, b) in world_a.birds().iter().zip(world_b.birds().iter()){
+            assert_eq!(a.position().x.to_bits(), b.position().x.to_bits());
+            assert_eq!(a.position().y.to_bits(), b.position().y.to_bits());
+            assert_eq!(a.angle().to_bits(), b.angle().to_bits());
+        }
+    }
+
+    #[test]
+    fn different_seed_different_spawn(){
+        let mut world_a = World::new(test_bounds(), BirdConfig::new(), 1);
+        let mut world_b = World::new(test_bounds(), BirdConfig::new(), 2);
+        let area = world_a.inner();
+        world_a.spawn(1, &area);
+        world_b.spawn(1, &area);
+
+        assert_ne!(world_a.birds()[0].position(), world_b.birds()[0].position());
+    }
+
+    #[test]
+    fn step_independent_of_bird_order(){
+        let mut world_a = World::new(test_bounds(), BirdConfig::new(), 99);
+        let area = world_a.inner();
+        world_a.spawn(30, &area);
+
+        /* Same flock with the birds in reverse order, neighbour sums will be in a different
+         * order so compare within a tolerance rather than bit for bit */
+        let mut world_b = World::new(test_bounds(), BirdConfig::new(), 99);
+        world_b.bird = world_a.bird.iter().rev().cloned().collect();
+
+        world_a.rebuild_grid();
+        world_b.rebuild_grid();
+        let steering_a = world_a.steering();
+        let steering_b = world_b.steering();
+
+        for (a, b) in steering_a.iter().zip(steering_b.iter().rev()){
+            assert!((a.alignment - b.alignment).abs() < 0.0001);
+            assert_eq!(a.separation.is_some(), b.separation.is_some());
+            assert_eq!(a.cohesion.is_some(), b.cohesion.is_some());
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_serial(){
+        let mut world_a = World::new(test_bounds(), BirdConfig::new(), 4321);
+        let mut world_b = World::new(test_bounds(), BirdConfig::new(), 4321);
+        world_a.set_parallel(false);
+        world_b.set_parallel(true);
+        let area = world_a.inner();
+        world_a.spawn(300, &area);
+        world_b.spawn(300, &area);
+
+        for _i in 0..200{
+            world_a.step();
+            world_b.step();
+        }
+
+        for (a, b) in world_a.birds().iter().zip(world_b.birds().iter()){
+            assert_eq!(a.position().x.to_bits(), b.position().x.to_bits());
+            assert_eq!(a.position().y.to_bits(), b.position().y.to_bits());
+            assert_eq!(a.angle().to_bits(), b.angle().to_bits());
+        }
+    }
+
+    #[test]
+    fn frame_rate_independent(){
+        /* Frame times that are exact in binary, so both worlds see exactly the same ticks */
+        let mut world_a = World::new(test_bounds(), BirdConfig::new(), 31);
+        let mut world_b = World::new(test_bounds(), BirdConfig::new(), 31);
+        world_a.set_tick_rate(64.0);
+        world_b.set_tick_rate(64.0);
+        let area = world_a.inner();
+        world_a.spawn(40, &area);
+        world_b.spawn(40, &area);
+
+        for _i in 0..256{
+            world_a.advance(1.0 / 128.0);
+        }
+        for _i in 0..64{
+            world_b.advance(1.0 / 32.0);
+        }
+
+        assert_eq!(world_a.meta().iterations(), 128);
+        assert_eq!(world_b.meta().iterations(), 128);
+        for (a, b) in world_a.birds().iter().zip(world_b.birds().iter()){
+            assert_eq!(a.position().x.to_bits(), b.position().x.to_bits());
+            assert_eq!(a.position().y.to_bits(), b.position().y.to_bits());
+            assert_eq!(a.angle().to_bits(), b.angle().to_bits());
+        }
+    }
+
+    #[test]
+    fn accumulate_drops_backlog(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 1);
+        world.set_tick_rate(64.0);
+
+        assert_eq!(world.accumulate(1.0 / 128.0), 0);
+        assert_eq!(world.accumulate(1.0 / 128.0), 1);
+        assert_eq!(world.accumulate(10.0), World::MAX_TICKS);
+        assert_eq!(world.accumulate(0.0), 0);
+    }
+
+    #[test]
+    fn accumulate_time_scale(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 1);
+        world.set_tick_rate(64.0);
+
+        world.set_time_scale(2.0);
+        assert_eq!(world.accumulate(1.0 / 64.0), 2);
+
+        world.set_time_scale(0.5);
+        assert_eq!(world.accumulate(1.0 / 64.0), 0);
+        assert_eq!(world.accumulate(1.0 / 64.0), 1);
+
+        /* Fast forward is allowed to catch up on more ticks per frame */
+        world.set_time_scale(10.0);
+        assert_eq!(world.accumulate(1.0 / 8.0), 80);
+    }
+
+    #[test]
+    fn rewind_and_replay(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 17);
+        let area = world.inner();
+        world.spawn(30, &area);
+        world.set_rewind(50);
+
+        for _i in 0..100{
+            world.step();
+        }
+        let latest:Vec<Point2> = world.birds().iter().map(|b| b.position()).collect();
+
+        for _i in 0..20{
+            assert!(world.step_back());
+        }
+        assert_eq!(world.rewound(), 20);
+        assert_eq!(world.meta().iterations(), 80);
+
+        /* Stepping on from a rewound world is deterministic, so it ends up in the same place */
+        for _i in 0..20{
+            world.step();
+        }
+        assert_eq!(world.rewound(), 0);
+        assert_eq!(world.meta().iterations(), 100);
+        for (bird, position) in world.birds().iter().zip(latest.iter()){
+            assert_eq!(bird.position(), *position);
+        }
+
+        /* Only the last 50 ticks are kept */
+        while world.step_back(){}
+        assert_eq!(world.meta().iterations(), 51);
+        while world.step_forward(){}
+        assert_eq!(world.meta().iterations(), 100);
+    }
+
+    #[test]
+    fn rewind_within_budget(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 17);
+        let area = world.inner();
+        world.spawn(20_000, &area);
+        world.set_rewind(300);
+
+        let snapshot = world.snapshot();
+        assert!(snapshot.size() < 1024 * 1024);
+
+        let budget = World::REWIND_BUDGET / snapshot.size();
+        for _i in 0..(budget + 5){
+            world.remember();
+        }
+        assert_eq!(world.history.len(), budget);
+
+        /* Asking for far more than fits doesn't try to set aside room for it all */
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 17);
+        world.spawn(20, &area);
+        world.set_rewind(usize::MAX);
+        world.step();
+        assert_eq!(world.history.len(), 2);
+    }
+
+    #[test]
+    fn steering_sees_nearby_obstacle(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 1);
+        world.bird.push(Bird::new(pt2(0.0, 0.0), 0.0, world.bird_config));
+        world.bird.push(Bird::new(pt2(600.0, 0.0), 0.0, world.bird_config));
+        world.set_obstacles(vec![Obstacle::circle(pt2(100.0, 0.0), 50.0)]);
+
+        world.rebuild_grid();
+        let steering = world.steering();
+
+        let avoidance = steering[0].avoidance.unwrap();
+        assert_eq!(avoidance.distance, 50.0);
+        assert_eq!(avoidance.angle, std::f32::consts::PI);
+        assert!(steering[1].avoidance.is_none());
+    }
+
+    #[test]
+    fn birds_flee_predator(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 23);
+        world.bird.push(Bird::new(pt2(0.0, 0.0), 0.0, world.bird_config));
+        world.predator.push(Predator::new(pt2(-100.0, -100.0), 0.0, PredatorConfig::new()));
+
+        world.rebuild_grid();
+        let steering = world.steering();
+        let threat = steering[0].threat.unwrap();
+        assert!((threat.angle - deg_to_rad(45.0)).abs() < 0.0001);
+
+        /* The lone bird ends up heading away from the predator that's chasing it */
+        for _i in 0..10{
+            world.step();
+        }
+        let away = world.birds()[0].position() - world.predators()[0].position();
+        let heading = vec2(world.birds()[0].angle().cos(), world.birds()[0].angle().sin());
+        assert!(away.dot(heading) > 0.0);
+    }
+
+    #[test]
+    fn spawn_predators_within_area(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 2);
+        let area = world.inner();
+        world.spawn_predators(3, PredatorConfig::new(), &area);
+
+        assert_eq!(world.predators().len(), 3);
+        for predator in world.predators(){
+            assert!(predator.position().x >= area.left() && predator.position().x <= area.right());
+            assert!(predator.position().y >= area.bottom() && predator.position().y <= area.top());
+        }
+    }
+
+    #[test]
+    fn pointer_lures_birds_in_range(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 4);
+        world.bird.push(Bird::new(pt2(0.0, 0.0), 0.0, world.bird_config));
+        world.bird.push(Bird::new(pt2(-800.0, 0.0), 0.0, world.bird_config));
+        world.set_pointer(Some(Pointer::Repel(pt2(100.0, 0.0))));
+
+        world.rebuild_grid();
+        let steering = world.steering();
+        let lure = steering[0].lure.unwrap();
+        assert_eq!(lure.angle, std::f32::consts::PI);
+        assert!(steering[1].lure.is_none());
+
+        world.set_pointer(None);
+        let steering = world.steering();
+        assert!(steering[0].lure.is_none());
+    }
+
+    #[test]
+    fn spawn_group_around_point(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 6);
+        world.spawn_group(10, pt2(200.0, -100.0), 20.0);
+
+        assert_eq!(world.birds().len(), 10);
+        for bird in world.birds(){
+            assert!((bird.position().x - 200.0).abs() <= 20.0);
+            assert!((bird.position().y + 100.0).abs() <= 20.0);
+            assert_eq!(bird.angle(), world.birds()[0].angle());
+        }
+    }
+
+    #[test]
+    fn topological_neighbours(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 12);
+        world.bird.push(Bird::new(pt2(0.0, 0.0), 0.0, world.bird_config));
+        world.bird.push(Bird::new(pt2(500.0, 0.0), deg_to_rad(90.0), world.bird_config));
+
+        /* Too far apart to see each other by distance, but each is the other's nearest */
+        world.rebuild_grid();
+        assert!(world.steering()[0].cohesion.is_none());
+
+        world.set_neighbour_mode(Neighbours::Topological{k: Neighbours::K});
+        let steering = world.steering();
+        assert!(steering[0].cohesion.is_some());
+        assert!(steering[0].alignment != 0.0);
+        assert!(steering[0].separation.is_none());
+    }
+
+    #[test]
+    fn inspect_neighbours(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 12);
+        for x in [0.0, 20.0, 200.0, 500.0]{
+            world.bird.push(Bird::new(pt2(x, 0.0), 0.0, world.bird_config));
+        }
+        assert_eq!(world.neighbours(0), (vec![1, 2], vec![1]));
+
+        /* Spawned since the last step */
+        world.spawn_group(1, pt2(-20.0, 0.0), 0.0);
+        assert_eq!(world.neighbours(4).1, vec![0]);
+
+        /* On a torus the way to a bird across the edge is the short way round */
+        world.set_boundary(Boundary::Torus);
+        world.rebuild_grid();
+        assert_eq!(world.offset(pt2(940.0, 0.0), pt2(-940.0, 0.0)), vec2(40.0, 0.0));
+    }
+
+    #[test]
+    fn vector_steering(){
+        let run = |steering_model:SteeringModel| {
+            let mut world = World::new(test_bounds(), BirdConfig::new(), 21);
+            world.set_steering_model(steering_model);
+            let area = world.inner();
+            world.spawn(40, &area);
+            for _i in 0..30{
+                world.step();
+            }
+            world.birds().iter().map(|b| (b.position(), b.angle())).collect::<Vec<_>>()
+        };
+
+        /* Same seed, same flock, but the models steer differently */
+        assert_eq!(run(SteeringModel::Vector), run(SteeringModel::Vector));
+        assert!(run(SteeringModel::Vector) != run(SteeringModel::Angular));
+
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 21);
+        world.toggle_steering_model();
+        assert_eq!(world.steering_model(), SteeringModel::Vector);
+        world.bird.push(Bird::new(pt2(0.0, 0.0), 0.0, world.bird_config));
+        world.bird.push(Bird::new(pt2(100.0, 0.0), deg_to_rad(90.0), world.bird_config));
+        world.rebuild_grid();
+        let steering = world.steering();
+        assert!(steering[0].force.is_some());
+        assert!(steering[0].cohesion.is_none());
+    }
+
+    #[test]
+    fn torus_sees_across_edges(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 3);
+        let right = world.bounds().right();
+        let left = world.bounds().left();
+        world.bird.push(Bird::new(pt2(right - 10.0, 0.0), 0.0, world.bird_config));
+        world.bird.push(Bird::new(pt2(left + 10.0, 0.0), deg_to_rad(90.0), world.bird_config));
+
+        world.rebuild_grid();
+        assert!(world.steering()[0].cohesion.is_none());
+
+        /* 20 apart round the back, so close enough to separate from too */
+        world.set_boundary(Boundary::Torus);
+        world.rebuild_grid();
+        let steering = world.steering();
+        let separation = steering[0].separation.unwrap();
+        assert!(angle::wrap_180(separation.0 - deg_to_rad(180.0)).abs() < 0.001);
+        let cohesion = steering[1].cohesion.unwrap();
+        assert!(angle::wrap_180(cohesion.0 - deg_to_rad(180.0)).abs() < 0.001);
+    }
+
+    #[test]
+    fn boundaries_contain_flock(){
+        for boundary in [Boundary::Torus, Boundary::TurnBox, Boundary::Reflect]{
+            let mut world = World::new(test_bounds(), BirdConfig::new(), 17);
+            world.set_boundary(boundary);
+            let area = world.inner();
+            world.spawn(30, &area);
+            for _i in 0..600{
+                world.step();
+            }
+
+            let extent = world.bounds().pad(-world.config().tuning.edge_bleed);
+            for bird in world.birds(){
+                let position = bird.position();
+                assert!(position.x >= extent.left() && position.x <= extent.right(), "{}", boundary.name());
+                assert!(position.y >= extent.bottom() && position.y <= extent.top(), "{}", boundary.name());
+                if !boundary.turns(){
+                    assert_eq!(bird.state(), State::Idle);
+                }
+            }
+        }
+
+        /* Nothing stops birds heading off for good */
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 17);
+        world.set_boundary(Boundary::Unbounded);
+        world.bird.push(Bird::new(pt2(0.0, 0.0), 0.0, world.bird_config));
+        for _i in 0..1200{
+            world.step();
+        }
+        assert!(world.birds()[0].position().x > world.bounds().right());
+    }
+
+    #[test]
+    fn unbounded_grid_is_capped(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 19);
+        world.set_boundary(Boundary::Unbounded);
+        world.spawn_group(10, pt2(0.0, 0.0), 50.0);
+        world.spawn_group(1, pt2(1.0e6, -1.0e6), 0.0);
+        world.step();
+
+        let cell_size = world.bird_config.tuning.region_radius;
+        let (extent, _) = world.grid_extent();
+        assert!(extent.w() <= World::MAX_GRID_CELLS * cell_size);
+        assert!(extent.h() <= World::MAX_GRID_CELLS * cell_size);
+
+        /* The straggler is still found by anything near it */
+        world.spawn_group(1, pt2(1.0e6, -1.0e6) + vec2(10.0, 0.0), 0.0);
+        let (nearby, _) = world.neighbours(11);
+        assert!(nearby.contains(&10));
+    }
+
+    #[test]
+    fn containment_keeps_flock_inside(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 23);
+        let circle = Containment::circle(pt2(-200.0, 0.0), 450.0);
+        world.set_containment(Some(circle.clone()));
+        assert_eq!(world.spawn_area(), circle.bounding_rect());
+
+        let area = world.spawn_area();
+        world.spawn(40, &area);
+        for _i in 0..900{
+            world.step();
+        }
+
+        /* They can overshoot a little while turning, but not by the whole turn offset */
+        for bird in world.birds(){
+            assert!(circle.surface(bird.position()).0 < World::TURN_OFFSET_HARD, "{:?}", bird.position());
+            assert_eq!(bird.state(), State::Idle);
+        }
+
+        /* Only the turn box turns back */
+        world.set_boundary(Boundary::Torus);
+        assert_eq!(world.spawn_area(), world.inner());
+        assert!(world.steering()[0].edge.is_none());
+    }
+
+    #[test]
+    fn step_measures_flock(){
+        let mut world = World::new(test_bounds(), BirdConfig::new(), 8);
+        let area = world.inner();
+        world.spawn(50, &area);
+        world.step();
+
+        let metrics = world.metrics();
+        assert!(metrics.polarization() >= 0.0 && metrics.polarization() <= 1.0);
+        assert!(metrics.milling() >= 0.0 && metrics.milling() <= 1.0);
+        assert!(metrics.nearest_neighbour() > 0.0);
+        assert!(metrics.radius() > 0.0);
+    }
+}